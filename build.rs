@@ -0,0 +1,15 @@
+//! Sets the `any_backend` cfg when at least one working backend is enabled. Without one there are
+//! no controls, so much of the crate goes unused, and that code allows `dead_code` on
+//! `not(any_backend)` rather than each file spelling out the list of backends again.
+
+use std::env;
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rustc-check-cfg=cfg(any_backend)");
+    let enabled = |feature: &str| env::var_os(format!("CARGO_FEATURE_{}", feature)).is_some();
+    let windows = env::var_os("CARGO_CFG_WINDOWS").is_some();
+    if (windows && enabled("EDGEHTML")) || enabled("GTK_WEBKIT2") || enabled("MOCK") {
+        println!("cargo:rustc-cfg=any_backend");
+    }
+}
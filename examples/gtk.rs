@@ -0,0 +1,41 @@
+use std::borrow::Cow;
+
+use gtk::prelude::*;
+
+use webviewcontrol::gtk::{Process, WindowWebView};
+use webviewcontrol::settings::ControlSettings;
+use webviewcontrol::WebView;
+
+fn main() {
+    gtk::init().expect("failed to initialise GTK");
+
+    let mut args = std::env::args();
+    // Ignore program name argument.
+    args.next();
+    let url: Cow<str> = args
+        .next()
+        .map(|url| url.into())
+        .unwrap_or("http://www.example.com".into());
+    println!("Opening a web view to {}", url);
+
+    let window = gtk::Window::new(gtk::WindowType::Toplevel);
+    window.set_title("It’s a WebView!");
+    window.set_default_size(800, 600);
+    // The window is only destroyed once the page has agreed to close.
+    window.connect_destroy(|_| gtk::main_quit());
+
+    let process = Process::new();
+    let web_view = WindowWebView::new(&process, &window, &ControlSettings::new()).unwrap();
+    let control = web_view.control().clone();
+    window.show_all();
+    control.focus().unwrap();
+
+    let url = url.into_owned();
+    gtk::glib::MainContext::default().spawn_local(async move {
+        let control = control.ready().await.expect("control creation failed");
+        println!("Control created!");
+        control.navigate(&url).unwrap();
+    });
+
+    gtk::main();
+}
@@ -1,13 +1,27 @@
+#[cfg(windows)]
 use std::borrow::Cow;
 
-use winit::event::{Event, WindowEvent};
-use winit::event_loop::{ControlFlow, EventLoop};
-use winit::platform::desktop::EventLoopExtDesktop;
-use winit::window::WindowBuilder;
+#[cfg(windows)]
+use winit::{
+    event::Event,
+    event_loop::{ControlFlow, EventLoop},
+    platform::desktop::EventLoopExtDesktop,
+    window::WindowBuilder,
+};
 
-use webviewcontrol::edge::{self, init_single_threaded_apartment, Control, Process, WebView};
-use webviewcontrol::edge_winit::{new_control, HwndType};
+#[cfg(windows)]
+use webviewcontrol::{
+    edge::{self, init_single_threaded_apartment, Process, WebView},
+    edge_winit::{CloseOutcome, WinitWebView},
+    settings::ControlSettings,
+};
 
+#[cfg(not(windows))]
+fn main() {
+    eprintln!("This example uses EdgeHTML, which is only available on Windows.");
+}
+
+#[cfg(windows)]
 fn main() {
     init_single_threaded_apartment();
 
@@ -31,42 +45,21 @@ fn main() {
         .unwrap();
 
     let process = Process::new();
-    let control = new_control(
-        &process,
-        &window,
-        HwndType::FillWindow,
-        None,
-        None,
-        Some(move |control: Control| {
-            println!("Control created!");
-            control.navigate(&url).unwrap();
-        }),
-    )
-    .unwrap();
-    control.focus();
+    let mut web_view = WinitWebView::new(&process, &window, &ControlSettings::new()).unwrap();
+    // The control isn’t ready yet, but operations are queued until it is.
+    web_view.control().navigate(&url).unwrap();
 
     event_loop.run_return(|event, _, control_flow| {
         *control_flow = ControlFlow::Wait;
-        match event {
-            Event::WindowEvent { window_id, event } => match event {
-                WindowEvent::Focused(false) => {
-                    println!("Window lost focus, TODO record whether control was focused");
-                }
-                WindowEvent::Focused(true) => {
-                    println!("Window gained focus, TODO only refocus control if it was before");
-                    control.focus();
-                }
-                WindowEvent::CloseRequested => {
-                    *control_flow = ControlFlow::Exit;
-                }
-                WindowEvent::Resized(size) => {
-                    let size: (u32, u32) = size.to_physical(window.hidpi_factor()).into();
-                    // Error in resizing? Meh.
-                    let _ = control.resize(None, Some((size.0 as i32, size.1 as i32)));
-                }
-                _ => (),
-            },
-            _ => (),
+        if let Event::WindowEvent { event, .. } = event {
+            if let Err(error) = web_view.handle_event(&event) {
+                eprintln!("Failed to handle {:?}: {}", event, error);
+            }
+        }
+        match web_view.poll_close() {
+            Some(CloseOutcome::Close) => *control_flow = ControlFlow::Exit,
+            Some(CloseOutcome::PageWantsToStay) => println!("The page asked to stay open"),
+            None => (),
         }
     });
 }
@@ -0,0 +1,390 @@
+//! Serving a directory of files embedded in the executable, through a custom scheme.
+//!
+//! The files are embedded at compile time by a build script:
+//!
+//! ```rust,ignore
+//! // build.rs, with webviewcontrol as a build dependency too
+//! fn main() {
+//!     webviewcontrol::assets::generate("web", "assets.rs").unwrap();
+//! }
+//! ```
+//!
+//! and then included and served with one call:
+//!
+//! ```rust,ignore
+//! static ASSETS: &[Asset] = webviewcontrol::include_assets!("assets.rs");
+//!
+//! control.serve_assets("app", Assets::new(ASSETS).with_spa_fallback(true))?;
+//! control.navigate("app://localhost/")?;
+//! ```
+//!
+//! Each response gets a `Content-Type` guessed from the file extension and an `ETag`, and
+//! conditional requests are answered with 304 Not Modified where the backend passes on request
+//! headers (see the `scheme` module).
+
+use std::collections::HashMap;
+use std::env;
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::scheme::{Body, SchemeRequest, SchemeResponse};
+
+/// A file to be served, as generated by `generate`.
+#[derive(Debug, Clone, Copy)]
+pub struct Asset {
+    /// The URL path, starting with a slash, e.g. `/js/app.js`.
+    pub path: &'static str,
+    pub bytes: &'static [u8],
+}
+
+/// Include the assets generated by `generate` with the same name, as a `&'static [Asset]`.
+#[macro_export]
+macro_rules! include_assets {
+    ($name:expr) => {
+        include!(concat!(env!("OUT_DIR"), "/", $name))
+    };
+}
+
+/// Generate the Rust source embedding every file in the directory, for `include_assets!`.
+///
+/// This is for build scripts: it writes the file `name` to `OUT_DIR`, and tells Cargo to rerun the
+/// build script if anything in the directory changes. A relative `dir` is taken relative to the
+/// package’s manifest directory.
+pub fn generate(dir: impl AsRef<Path>, name: &str) -> io::Result<()> {
+    let out_dir = env::var_os("OUT_DIR")
+        .ok_or_else(|| io::Error::other("OUT_DIR is not set; generate is for build scripts"))?;
+    let mut dir = dir.as_ref().to_owned();
+    if dir.is_relative() {
+        if let Some(manifest_dir) = env::var_os("CARGO_MANIFEST_DIR") {
+            dir = Path::new(&manifest_dir).join(dir);
+        }
+    }
+    let dir = dir.canonicalize()?;
+
+    let mut files = Vec::new();
+    collect_files(&dir, &mut files)?;
+    files.sort();
+
+    let mut source = String::from("&[\n");
+    for file in &files {
+        let relative = file
+            .strip_prefix(&dir)
+            .expect("file is within the directory");
+        let path: Vec<_> = relative
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect();
+        writeln!(
+            source,
+            "    ::webviewcontrol::assets::Asset {{ path: {:?}, bytes: include_bytes!({:?}) }},",
+            format!("/{}", path.join("/")),
+            file.to_string_lossy(),
+        )
+        .expect("writing to a String can’t fail");
+    }
+    source.push_str("]\n");
+
+    println!("cargo:rerun-if-changed={}", dir.display());
+    fs::write(Path::new(&out_dir).join(name), source)
+}
+
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            println!("cargo:rerun-if-changed={}", path.display());
+            collect_files(&path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+struct File {
+    bytes: &'static [u8],
+    etag: String,
+}
+
+/// A set of assets to serve, with `WebView::serve_assets`.
+pub struct Assets {
+    files: HashMap<&'static str, File>,
+    spa_fallback: bool,
+}
+
+impl Assets {
+    pub fn new(assets: &[Asset]) -> Assets {
+        Assets {
+            files: assets
+                .iter()
+                .map(|asset| {
+                    let file = File {
+                        bytes: asset.bytes,
+                        etag: format!("\"{:016x}\"", fnv1a(asset.bytes)),
+                    };
+                    (asset.path, file)
+                })
+                .collect(),
+            spa_fallback: false,
+        }
+    }
+
+    /// Serve `/index.html` for paths that match no file and don’t look like file names (that is,
+    /// whose last segment has no `.`), so that a single-page app can do its own routing.
+    pub fn with_spa_fallback(mut self, spa_fallback: bool) -> Assets {
+        self.spa_fallback = spa_fallback;
+        self
+    }
+
+    /// Respond to a request. Directory paths (ending in `/`) get the directory’s `index.html`.
+    pub fn respond(&self, request: &SchemeRequest) -> SchemeResponse {
+        let head = match &*request.method {
+            "GET" => false,
+            "HEAD" => true,
+            _ => return SchemeResponse::new(405, Body::empty()).with_header("Allow", "GET, HEAD"),
+        };
+
+        let mut path = percent_decode(&request.path);
+        if path.ends_with('/') {
+            path.push_str("index.html");
+        }
+        let (path, file) = match self.files.get_key_value(&*path) {
+            Some((&path, file)) => (path, file),
+            None => match self.files.get_key_value("/index.html") {
+                Some((&index, file)) if self.spa_fallback && !looks_like_file(&path) => {
+                    (index, file)
+                }
+                _ => return SchemeResponse::not_found(),
+            },
+        };
+
+        let not_modified = request.header("If-None-Match").is_some_and(|tags| {
+            tags.split(',')
+                .any(|tag| tag.trim() == file.etag || tag.trim() == "*")
+        });
+        let response = if not_modified {
+            SchemeResponse::new(304, Body::empty())
+        } else if head {
+            SchemeResponse::ok(Body::empty())
+        } else {
+            SchemeResponse::ok(file.bytes)
+        };
+        response
+            .with_header("Content-Type", content_type(path))
+            .with_header("ETag", file.etag.clone())
+            // Always revalidate, which costs nothing but a call to the handler.
+            .with_header("Cache-Control", "no-cache")
+    }
+}
+
+/// Guess a file’s `Content-Type` from its extension, falling back to `application/octet-stream`.
+pub fn content_type(path: &str) -> &'static str {
+    let extension = match path.rfind('.') {
+        Some(dot) if !path[dot..].contains('/') => path[dot + 1..].to_ascii_lowercase(),
+        _ => return "application/octet-stream",
+    };
+    match &*extension {
+        "html" | "htm" => "text/html; charset=utf-8",
+        "css" => "text/css; charset=utf-8",
+        "js" | "mjs" => "text/javascript; charset=utf-8",
+        "json" | "map" => "application/json",
+        "txt" => "text/plain; charset=utf-8",
+        "xml" => "application/xml",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "ico" => "image/x-icon",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "ttf" => "font/ttf",
+        "otf" => "font/otf",
+        "wasm" => "application/wasm",
+        "mp3" => "audio/mpeg",
+        "mp4" => "video/mp4",
+        "webm" => "video/webm",
+        "pdf" => "application/pdf",
+        _ => "application/octet-stream",
+    }
+}
+
+fn looks_like_file(path: &str) -> bool {
+    path.rsplit('/')
+        .next()
+        .is_some_and(|last| last.contains('.'))
+}
+
+/// Decode `%XX` escapes in a URL path, leaving any invalid ones as they are.
+fn percent_decode(path: &str) -> String {
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escape = if bytes[i] == b'%' {
+            path.get(i + 1..i + 3)
+                .filter(|hex| hex.bytes().all(|byte| byte.is_ascii_hexdigit()))
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+        } else {
+            None
+        };
+        match escape {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// The 64-bit FNV-1a hash, which is plenty for ETags and, unlike std’s hashers, stable.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ASSETS: &[Asset] = &[
+        Asset {
+            path: "/index.html",
+            bytes: b"<!doctype html>",
+        },
+        Asset {
+            path: "/js/app.js",
+            bytes: b"app()",
+        },
+        Asset {
+            path: "/docs/index.html",
+            bytes: b"docs",
+        },
+        Asset {
+            path: "/a file.txt",
+            bytes: b"spaced",
+        },
+    ];
+
+    fn request(method: &str, path: &str, headers: &[(&str, &str)]) -> SchemeRequest {
+        SchemeRequest {
+            method: method.to_owned(),
+            uri: format!("app://localhost{}", path),
+            path: path.to_owned(),
+            headers: headers
+                .iter()
+                .map(|&(name, value)| (name.to_owned(), value.to_owned()))
+                .collect(),
+        }
+    }
+
+    fn body(response: SchemeResponse) -> Vec<u8> {
+        response.body.into_bytes().unwrap().into_owned()
+    }
+
+    #[test]
+    fn percent_decoding() {
+        assert_eq!(percent_decode("/a%20file.txt"), "/a file.txt");
+        assert_eq!(percent_decode("/%E2%9C%93"), "/✓");
+        assert_eq!(percent_decode("/%2f%2F"), "///");
+        // Invalid escapes are left alone.
+        assert_eq!(percent_decode("/100%"), "/100%");
+        assert_eq!(percent_decode("/%zz%4"), "/%zz%4");
+        assert_eq!(percent_decode("/%%41"), "/%A");
+    }
+
+    #[test]
+    fn content_types() {
+        assert_eq!(content_type("/index.html"), "text/html; charset=utf-8");
+        assert_eq!(content_type("/js/APP.JS"), "text/javascript; charset=utf-8");
+        assert_eq!(content_type("/image.svg"), "image/svg+xml");
+        assert_eq!(content_type("/unknown.xyz"), "application/octet-stream");
+        assert_eq!(content_type("/no-extension"), "application/octet-stream");
+        assert_eq!(content_type("/dotted.dir/file"), "application/octet-stream");
+    }
+
+    #[test]
+    fn serving() {
+        let assets = Assets::new(ASSETS);
+        let response = assets.respond(&request("GET", "/js/app.js", &[]));
+        assert_eq!(response.status, 200);
+        assert_eq!(
+            response.header("content-type"),
+            Some("text/javascript; charset=utf-8")
+        );
+        assert_eq!(response.header("Cache-Control"), Some("no-cache"));
+        assert_eq!(body(response), b"app()");
+
+        assert_eq!(
+            body(assets.respond(&request("GET", "/", &[]))),
+            b"<!doctype html>"
+        );
+        assert_eq!(
+            body(assets.respond(&request("GET", "/docs/", &[]))),
+            b"docs"
+        );
+        assert_eq!(
+            body(assets.respond(&request("GET", "/a%20file.txt", &[]))),
+            b"spaced"
+        );
+        assert_eq!(assets.respond(&request("GET", "/missing", &[])).status, 404);
+
+        let response = assets.respond(&request("HEAD", "/js/app.js", &[]));
+        assert_eq!(response.status, 200);
+        assert!(response.header("ETag").is_some());
+        assert!(body(response).is_empty());
+
+        let response = assets.respond(&request("POST", "/js/app.js", &[]));
+        assert_eq!(response.status, 405);
+        assert_eq!(response.header("Allow"), Some("GET, HEAD"));
+    }
+
+    #[test]
+    fn etags() {
+        let assets = Assets::new(ASSETS);
+        let etag = assets
+            .respond(&request("GET", "/js/app.js", &[]))
+            .header("ETag")
+            .unwrap()
+            .to_owned();
+        assert_eq!(etag, format!("\"{:016x}\"", fnv1a(b"app()")));
+        assert_ne!(
+            Some(&*etag),
+            assets.respond(&request("GET", "/", &[])).header("ETag")
+        );
+
+        let revalidate = |tags: &str| {
+            assets
+                .respond(&request("GET", "/js/app.js", &[("If-None-Match", tags)]))
+                .status
+        };
+        assert_eq!(revalidate(&etag), 304);
+        assert_eq!(revalidate(&format!("\"other\", {}", etag)), 304);
+        assert_eq!(revalidate("*"), 304);
+        assert_eq!(revalidate("\"other\""), 200);
+    }
+
+    #[test]
+    fn spa_fallback() {
+        let assets = Assets::new(ASSETS).with_spa_fallback(true);
+        let response = assets.respond(&request("GET", "/users/42", &[]));
+        assert_eq!(response.status, 200);
+        assert_eq!(
+            response.header("Content-Type"),
+            Some("text/html; charset=utf-8")
+        );
+        assert_eq!(body(response), b"<!doctype html>");
+        assert_eq!(
+            assets.respond(&request("GET", "/missing.js", &[])).status,
+            404
+        );
+    }
+}
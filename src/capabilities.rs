@@ -0,0 +1,137 @@
+//! What a control’s engine can do, for degrading gracefully where it can’t.
+//!
+//! ```rust,ignore
+//! if control.capabilities().resource_interception {
+//!     control.add_web_resource_requested(stub_api)?.detach();
+//! } else {
+//!     control.navigate("https://staging.example.com/")?;
+//! }
+//! ```
+//!
+//! Operations that need a capability the engine lacks fail with `Error::Unsupported`, rather than
+//! quietly doing nothing.
+
+use crate::error::Error;
+
+/// One of the things in `Capabilities`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Capability {
+    InitScripts,
+    InitScriptRemoval,
+    CustomSchemes,
+    Capture,
+    Printing,
+    DevTools,
+    ResourceInterception,
+    UserZoomEvents,
+}
+
+/// What a control’s engine can do.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Capabilities {
+    /// `add_init_script` works.
+    pub init_scripts: bool,
+    /// Dropping the subscription from `add_init_script` removes the script. EdgeHTML can only
+    /// remove scripts that it hasn’t been given yet, i.e. before the control has been created.
+    pub init_script_removal: bool,
+    /// `register_scheme` and `serve_assets` work.
+    pub custom_schemes: bool,
+    /// `capture_image` works.
+    pub capture: bool,
+    /// `print` works.
+    pub printing: bool,
+    /// `ControlSettings::dev_tools_enabled` can let the user open the developer tools.
+    pub dev_tools: bool,
+    /// `add_web_resource_requested` works.
+    pub resource_interception: bool,
+    /// Zooming by the user raises the handlers from `add_zoom_changed`, not only `set_zoom`.
+    pub user_zoom_events: bool,
+}
+
+impl Capabilities {
+    /// Everything, as the mock backend has by default.
+    pub const ALL: Capabilities = Capabilities {
+        init_scripts: true,
+        init_script_removal: true,
+        custom_schemes: true,
+        capture: true,
+        printing: true,
+        dev_tools: true,
+        resource_interception: true,
+        user_zoom_events: true,
+    };
+
+    /// Whether the engine can do that.
+    pub fn supports(&self, capability: Capability) -> bool {
+        match capability {
+            Capability::InitScripts => self.init_scripts,
+            Capability::InitScriptRemoval => self.init_script_removal,
+            Capability::CustomSchemes => self.custom_schemes,
+            Capability::Capture => self.capture,
+            Capability::Printing => self.printing,
+            Capability::DevTools => self.dev_tools,
+            Capability::ResourceInterception => self.resource_interception,
+            Capability::UserZoomEvents => self.user_zoom_events,
+        }
+    }
+
+    /// Fail with `Error::Unsupported` if the engine can’t do that.
+    #[cfg_attr(not(any_backend), allow(dead_code))]
+    pub(crate) fn require(&self, capability: Capability) -> Result<(), Error> {
+        if self.supports(capability) {
+            Ok(())
+        } else {
+            Err(Error::Unsupported(capability))
+        }
+    }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use super::*;
+    use crate::mock::Process;
+    use crate::settings::ControlSettings;
+    use crate::WebView;
+
+    #[test]
+    fn require() {
+        assert!(Capabilities::ALL.require(Capability::Printing).is_ok());
+        let capabilities = Capabilities {
+            printing: false,
+            ..Capabilities::ALL
+        };
+        match capabilities.require(Capability::Printing) {
+            Err(Error::Unsupported(Capability::Printing)) => {}
+            result => panic!("expected Unsupported(Printing), got {:?}", result),
+        }
+        assert!(capabilities.require(Capability::Capture).is_ok());
+    }
+
+    #[test]
+    fn operations_needing_a_missing_capability_are_unsupported() {
+        let control = Process::new()
+            .create_control((0, 0), (800, 600), &ControlSettings::default())
+            .unwrap();
+        control.complete_creation();
+        control.set_capabilities(Capabilities {
+            printing: false,
+            init_scripts: false,
+            resource_interception: false,
+            ..Capabilities::ALL
+        });
+        assert!(matches!(
+            control.print(),
+            Err(Error::Unsupported(Capability::Printing))
+        ));
+        assert!(matches!(
+            control.add_init_script("void 0"),
+            Err(Error::Unsupported(Capability::InitScripts))
+        ));
+        assert!(matches!(
+            control.add_web_resource_requested(|_request| {}),
+            Err(Error::Unsupported(Capability::ResourceInterception))
+        ));
+        assert_eq!(control.print_count(), 0);
+        assert!(control.init_scripts().is_empty());
+    }
+}
@@ -0,0 +1,388 @@
+//! Capturing what a control is showing as an image, e.g. for thumbnails or visual tests.
+//!
+//! ```rust,ignore
+//! let image = control.capture_image(None).await?;
+//! std::fs::write("screenshot.png", image.to_png())?;
+//! ```
+//!
+//! The image is of the part of the page that is visible in the control, in physical pixels.
+
+use std::cell::RefCell;
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll, Waker};
+
+use crate::error::Error;
+
+/// A rectangle of an image, in pixels from its top left corner.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// An image, as 8-bit RGBA pixels (not premultiplied), row by row from the top left.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    pub rgba: Vec<u8>,
+}
+
+impl Image {
+    /// The part of the image within the rectangle, which is first reduced to fit the image.
+    pub fn crop(&self, rect: Rect) -> Image {
+        let x = rect.x.min(self.width);
+        let y = rect.y.min(self.height);
+        let width = rect.width.min(self.width - x);
+        let height = rect.height.min(self.height - y);
+        let mut rgba = Vec::with_capacity(width as usize * height as usize * 4);
+        for row in y..y + height {
+            let start = (row as usize * self.width as usize + x as usize) * 4;
+            rgba.extend_from_slice(&self.rgba[start..start + width as usize * 4]);
+        }
+        Image {
+            width,
+            height,
+            rgba,
+        }
+    }
+
+    /// Encode the image as a PNG file.
+    ///
+    /// The image data is stored without compression, which is quick and simple but makes for
+    /// large files; recompress them with another tool if that matters.
+    pub fn to_png(&self) -> Vec<u8> {
+        let row_len = self.width as usize * 4;
+        // Each row is preceded by its filter type, which is always None.
+        let mut raw = Vec::with_capacity((row_len + 1) * self.height as usize);
+        for row in self.rgba.chunks(row_len.max(1)).take(self.height as usize) {
+            raw.push(0);
+            raw.extend_from_slice(row);
+        }
+
+        let mut header = Vec::with_capacity(13);
+        header.extend_from_slice(&self.width.to_be_bytes());
+        header.extend_from_slice(&self.height.to_be_bytes());
+        // 8 bits per channel, RGBA, deflate, standard filtering, not interlaced.
+        header.extend_from_slice(&[8, 6, 0, 0, 0]);
+
+        let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+        write_chunk(&mut png, b"IHDR", &header);
+        write_chunk(&mut png, b"IDAT", &zlib_stored(&raw));
+        write_chunk(&mut png, b"IEND", &[]);
+        png
+    }
+
+    /// Convert from 32-bit pixels in native byte order with premultiplied alpha in the top byte,
+    /// which is Cairo’s ARGB32 format.
+    #[cfg(feature = "gtk-webkit2")]
+    pub(crate) fn from_premultiplied_argb(
+        width: u32,
+        height: u32,
+        stride: usize,
+        data: &[u8],
+    ) -> Image {
+        let mut rgba = Vec::with_capacity(width as usize * height as usize * 4);
+        for row in data.chunks(stride).take(height as usize) {
+            for pixel in row.chunks_exact(4).take(width as usize) {
+                let argb = u32::from_ne_bytes([pixel[0], pixel[1], pixel[2], pixel[3]]);
+                let alpha = (argb >> 24) as u8;
+                let unpremultiply = |channel: u32| {
+                    let channel = (channel & 0xff) as u8;
+                    match alpha {
+                        0 => 0,
+                        255 => channel,
+                        _ => ((u32::from(channel) * 255 + u32::from(alpha) / 2) / u32::from(alpha))
+                            .min(255) as u8,
+                    }
+                };
+                rgba.extend_from_slice(&[
+                    unpremultiply(argb >> 16),
+                    unpremultiply(argb >> 8),
+                    unpremultiply(argb),
+                    alpha,
+                ]);
+            }
+        }
+        Image {
+            width,
+            height,
+            rgba,
+        }
+    }
+}
+
+fn write_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    png.extend_from_slice(kind);
+    png.extend_from_slice(data);
+    let crc = crc32(kind.iter().chain(data));
+    png.extend_from_slice(&crc.to_be_bytes());
+}
+
+/// Wrap the data in a zlib stream of stored (uncompressed) deflate blocks.
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    const MAX_BLOCK: usize = 0xffff;
+    let mut zlib = Vec::with_capacity(data.len() + data.len() / MAX_BLOCK * 5 + 11);
+    // Deflate with a 32K window, no preset dictionary, and a check value to suit.
+    zlib.extend_from_slice(&[0x78, 0x01]);
+    let mut blocks = data.chunks(MAX_BLOCK).peekable();
+    if blocks.peek().is_none() {
+        zlib.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        let is_final = blocks.peek().is_none();
+        let len = block.len() as u16;
+        zlib.push(is_final as u8);
+        zlib.extend_from_slice(&len.to_le_bytes());
+        zlib.extend_from_slice(&(!len).to_le_bytes());
+        zlib.extend_from_slice(block);
+    }
+    zlib.extend_from_slice(&adler32(data).to_be_bytes());
+    zlib
+}
+
+fn crc32<'a>(bytes: impl Iterator<Item = &'a u8>) -> u32 {
+    !bytes.fold(!0, |crc, &byte| {
+        (0..8).fold(crc ^ u32::from(byte), |crc, _| {
+            if crc & 1 == 1 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            }
+        })
+    })
+}
+
+fn adler32(bytes: &[u8]) -> u32 {
+    let (a, b) = bytes.iter().fold((1, 0), |(a, b), &byte| {
+        let a = (a + u32::from(byte)) % 65521;
+        (a, (b + a) % 65521)
+    });
+    (b << 16) | a
+}
+
+struct Shared {
+    result: Option<Result<Image, Error>>,
+    waker: Option<Waker>,
+}
+
+/// A future that resolves to an image of what a control is showing.
+///
+/// This is returned by `WebView::capture_image`. It must be polled on the thread the control was
+/// created on.
+#[must_use = "futures do nothing unless polled"]
+pub struct Capture {
+    shared: Rc<RefCell<Shared>>,
+}
+
+impl Capture {
+    /// A capture that failed before it could even be started: with EdgeHTML, because it couldn’t
+    /// be set up; with the mock backend, because capture was made unsupported.
+    #[cfg_attr(
+        not(any(all(windows, feature = "edgehtml"), feature = "mock")),
+        allow(dead_code)
+    )]
+    pub(crate) fn failed(error: Error) -> Capture {
+        Capture {
+            shared: Rc::new(RefCell::new(Shared {
+                result: Some(Err(error)),
+                waker: None,
+            })),
+        }
+    }
+}
+
+impl Future for Capture {
+    type Output = Result<Image, Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<Image, Error>> {
+        let mut shared = self.shared.borrow_mut();
+        match shared.result.take() {
+            Some(result) => Poll::Ready(result),
+            None => {
+                shared.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// The backend’s end of a `Capture`.
+pub(crate) struct Resolver {
+    shared: Option<Rc<RefCell<Shared>>>,
+    clip: Option<Rect>,
+}
+
+/// Create a `Capture` and the `Resolver` for the backend to complete it with, which crops the
+/// image to the clip rectangle if there is one.
+#[cfg_attr(not(any_backend), allow(dead_code))]
+pub(crate) fn channel(clip: Option<Rect>) -> (Resolver, Capture) {
+    let shared = Rc::new(RefCell::new(Shared {
+        result: None,
+        waker: None,
+    }));
+    (
+        Resolver {
+            shared: Some(shared.clone()),
+            clip,
+        },
+        Capture { shared },
+    )
+}
+
+impl Resolver {
+    /// Complete the capture with the whole image, or an error.
+    pub(crate) fn resolve(mut self, result: Result<Image, Error>) {
+        if let Some(shared) = self.shared.take() {
+            let result = match self.clip {
+                Some(clip) => result.map(|image| image.crop(clip)),
+                None => result,
+            };
+            let mut shared = shared.borrow_mut();
+            shared.result = Some(result);
+            if let Some(waker) = shared.waker.take() {
+                waker.wake();
+            }
+        }
+    }
+}
+
+impl Drop for Resolver {
+    // The backend dropped the capture without making it, which happens when the control could not
+    // be created.
+    fn drop(&mut self) {
+        if let Some(shared) = self.shared.take() {
+            Resolver {
+                shared: Some(shared),
+                clip: None,
+            }
+            .resolve(Err(Error::CreationFailed));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An image whose every pixel is its own index, for telling them apart.
+    fn numbered(width: u32, height: u32) -> Image {
+        Image {
+            width,
+            height,
+            rgba: (0..width * height).flat_map(|i| [i as u8; 4]).collect(),
+        }
+    }
+
+    #[test]
+    fn checksums() {
+        assert_eq!(crc32(b"123456789".iter()), 0xcbf4_3926);
+        assert_eq!(crc32(b"IEND".iter()), 0xae42_6082);
+        assert_eq!(adler32(b"Wikipedia"), 0x11e6_0398);
+        assert_eq!(adler32(b""), 1);
+    }
+
+    /// Undo `zlib_stored`, checking the framing as it goes.
+    fn unstore(zlib: &[u8]) -> Vec<u8> {
+        assert_eq!(&zlib[..2], [0x78, 0x01]);
+        assert_eq!((u16::from(zlib[0]) << 8 | u16::from(zlib[1])) % 31, 0);
+        let mut data = Vec::new();
+        let mut rest = &zlib[2..];
+        loop {
+            let is_final = rest[0] == 1;
+            let len = u16::from_le_bytes([rest[1], rest[2]]);
+            assert_eq!(!len, u16::from_le_bytes([rest[3], rest[4]]));
+            data.extend_from_slice(&rest[5..5 + len as usize]);
+            rest = &rest[5 + len as usize..];
+            if is_final {
+                break;
+            }
+        }
+        assert_eq!(rest, adler32(&data).to_be_bytes());
+        data
+    }
+
+    #[test]
+    fn zlib_stored_blocks() {
+        assert!(unstore(&zlib_stored(&[])).is_empty());
+        assert_eq!(unstore(&zlib_stored(b"hello")), b"hello");
+        let big: Vec<u8> = (0..200_000u32).map(|i| i as u8).collect();
+        assert_eq!(unstore(&zlib_stored(&big)), big);
+    }
+
+    #[test]
+    fn png() {
+        let image = numbered(2, 2);
+        let png = image.to_png();
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+
+        // Walk the chunks, checking each one’s CRC.
+        let mut chunks = Vec::new();
+        let mut rest = &png[8..];
+        while !rest.is_empty() {
+            let len = u32::from_be_bytes([rest[0], rest[1], rest[2], rest[3]]) as usize;
+            let kind = &rest[4..8];
+            let data = &rest[8..8 + len];
+            let crc = &rest[8 + len..12 + len];
+            assert_eq!(crc, crc32(kind.iter().chain(data)).to_be_bytes());
+            chunks.push((kind.to_vec(), data.to_vec()));
+            rest = &rest[12 + len..];
+        }
+        let kinds: Vec<&[u8]> = chunks.iter().map(|(kind, _)| &kind[..]).collect();
+        assert_eq!(kinds, [&b"IHDR"[..], b"IDAT", b"IEND"]);
+        assert_eq!(chunks[0].1, [0, 0, 0, 2, 0, 0, 0, 2, 8, 6, 0, 0, 0]);
+        assert_eq!(
+            unstore(&chunks[1].1),
+            [0, 0, 0, 0, 0, 1, 1, 1, 1, 0, 2, 2, 2, 2, 3, 3, 3, 3]
+        );
+        assert!(chunks[2].1.is_empty());
+    }
+
+    #[test]
+    fn crop() {
+        let image = numbered(3, 3);
+        let cropped = image.crop(Rect {
+            x: 1,
+            y: 1,
+            width: 2,
+            height: 1,
+        });
+        assert_eq!(
+            cropped,
+            Image {
+                width: 2,
+                height: 1,
+                rgba: vec![4, 4, 4, 4, 5, 5, 5, 5],
+            }
+        );
+
+        // The rectangle is reduced to fit the image.
+        let cropped = image.crop(Rect {
+            x: 2,
+            y: 1,
+            width: 10,
+            height: 10,
+        });
+        assert_eq!(
+            cropped,
+            Image {
+                width: 1,
+                height: 2,
+                rgba: vec![5, 5, 5, 5, 8, 8, 8, 8],
+            }
+        );
+        let cropped = image.crop(Rect {
+            x: 5,
+            y: 5,
+            width: 1,
+            height: 1,
+        });
+        assert_eq!((cropped.width, cropped.height), (0, 0));
+        assert!(cropped.rgba.is_empty());
+    }
+}
@@ -3,9 +3,9 @@
 //! This uses Windows.Web.UI.Interop.WebViewControl, and requires at a minimum the October 2018
 //! build of Windows 10, 17763. (TODO: support detecting whether it’ll work ahead of time.)
 //!
-//! Most of the Windows.Web.UI.Interop namespace was added in build 17083, but that may not be
-//! sufficient for this library, which may depend on AddInitializeScript and perhaps GotFocus and
-//! LostFocus, all of which were only introduced in build 17763.
+//! Most of the Windows.Web.UI.Interop namespace was added in build 17083, but that is not
+//! sufficient for this library, which depends on AddInitializeScript (and may depend on GotFocus
+//! and LostFocus), all of which were only introduced in build 17763.
 //!
 //! See https://docs.microsoft.com/en-us/microsoft-edge/dev-guide#webview for some details of
 //! limitations (e.g. Push API isn’t supported). Further known limitations:
@@ -33,32 +33,96 @@
 //! incorrect.) But if you really just want a Rust UWP EdgeHTML-powered window with no other
 //! controls, https://github.com/quadrupleslap/tether is probably a good place to look.
 
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::future::Future;
 use std::io;
 use std::mem;
+use std::pin::Pin;
 use std::ptr;
 use std::rc::Rc;
+use std::task::{Context, Poll};
 
+use raw_window_handle::{HasRawWindowHandle, RawWindowHandle};
+use serde::de::DeserializeOwned;
 use winapi::shared::minwindef::UINT;
 use winapi::shared::windef::{HWND, RECT};
 use winapi::um::winnt::LPCWSTR;
 use winapi::um::winuser;
 
+use winrt::windows::applicationmodel::datatransfer::DataPackage;
 use winrt::windows::foundation::{
-    metadata::ApiInformation, AsyncOperationCompletedHandler, EventRegistrationToken, Rect,
-    TypedEventHandler, Uri,
+    collections::{IIterable, IKeyValuePair, IMap},
+    metadata::ApiInformation,
+    AsyncActionCompletedHandler, AsyncOperationCompletedHandler, Deferral, IAsyncAction,
+    IAsyncOperation, Rect, TypedEventHandler, Uri,
+};
+use winrt::windows::graphics::imaging::{
+    BitmapAlphaMode, BitmapDecoder, BitmapPixelFormat, BitmapTransform, ColorManagementMode,
+    ExifOrientationMode, IBitmapFrame, PixelDataProvider,
+};
+use winrt::windows::storage::streams::{
+    DataWriter, IRandomAccessStream, InMemoryRandomAccessStream,
+};
+use winrt::windows::ui::Color as WinRtColor;
+use winrt::windows::web::http::{
+    headers::HttpRequestHeaderCollection, HttpBufferContent, HttpRequestMessage,
+    HttpResponseMessage, HttpStatusCode,
 };
 use winrt::windows::web::ui::{
     interop::{
-        IWebViewControlSite, WebViewControl, WebViewControlMoveFocusReason, WebViewControlProcess,
+        IWebViewControlSite, IWebViewControlSite2, WebViewControl, WebViewControlMoveFocusReason,
+        WebViewControlProcess,
     },
-    IWebViewControl,
-    //IWebViewControl2,
-    WebViewControlScriptNotifyEventArgs,
+    IWebViewControl, IWebViewControl2, IWebViewControlNewWindowRequestedEventArgs2,
+    WebViewControlContentLoadingEventArgs, WebViewControlDOMContentLoadedEventArgs,
+    WebViewControlDeferredPermissionRequest, WebViewControlNavigationCompletedEventArgs,
+    WebViewControlNavigationStartingEventArgs, WebViewControlNewWindowRequestedEventArgs,
+    WebViewControlPermissionRequestedEventArgs, WebViewControlPermissionType,
+    WebViewControlScriptNotifyEventArgs, WebViewControlWebResourceRequestedEventArgs,
 };
-use winrt::{ApartmentType, ComPtr, FastHString, RtDefaultConstructible};
+use winrt::windows::web::{IUriToStreamResolver, WebErrorStatus};
+use winrt::{ApartmentType, ComPtr, FastHString, HString, RtDefaultConstructible};
 
+use crate::capabilities::{Capabilities, Capability};
+use crate::capture::{self, Capture, Image};
+use crate::edge_scheme;
 use crate::error::Error;
+use crate::navigation::{
+    self, ContentLoading, DomContentLoaded, NavigationCompleted, NavigationStarting,
+    NewWindowAction, NewWindowRequested, WebErrorKind,
+};
+use crate::permission::{PermissionKind, PermissionRequest, PermissionState};
+use crate::ready::Readiness;
+use crate::resource::{ResourceAction, WebResourceRequest};
+use crate::scheme::{self, SchemeRequest, SchemeResponse};
+use crate::script::{self, Eval};
+use crate::settings::{ControlSettings, Setting};
+use crate::subscription::Subscription;
+use crate::zoom;
+use crate::Color;
+pub use crate::WebView;
+
+/// The settings in `ControlSettings` that EdgeHTML can honour.
+const SUPPORTED_SETTINGS: &[Setting] = &[
+    Setting::JavaScript,
+    Setting::ScriptNotify,
+    Setting::IndexedDb,
+];
+
+/// What EdgeHTML can do. It has no developer tools of its own (the separate Edge DevTools app has
+/// to be used instead), can’t print or report zooming by the user, and only takes initialization
+/// scripts one way.
+const CAPABILITIES: Capabilities = Capabilities {
+    init_scripts: true,
+    init_script_removal: false,
+    custom_schemes: true,
+    capture: true,
+    printing: false,
+    dev_tools: false,
+    resource_interception: true,
+    user_zoom_events: false,
+};
 
 /// Dangerously pretend that the wrapped value is Send.
 ///
@@ -97,14 +161,276 @@ static HOST_CLASS_NAME: [u16; 20] = [
     0,
 ];
 
+/// Make an `IIterable<HString>`, as needed for script arguments.
+///
+/// The winrt crate doesn’t give us any way of making a collection of our own, so we borrow one
+/// that a WinRT class will make for us: the file types list of a data package’s properties.
+fn string_iterable(strings: &[&str]) -> Result<ComPtr<IIterable<HString>>, winrt::Error> {
+    // DataPackagePropertySet.FileTypes is always present, but failing beats panicking.
+    let mut vector = DataPackage::new()
+        .get_properties()?
+        .ok_or(winrt::Error::UnspecifiedFailure)?
+        .get_file_types()?
+        .ok_or(winrt::Error::UnspecifiedFailure)?;
+    for string in strings {
+        vector.append(&FastHString::from(*string))?;
+    }
+    vector
+        .query_interface::<IIterable<HString>>()
+        .ok_or(winrt::Error::NoSuchInterface)
+}
+
+/// The absolute form of a URI from an event, or an empty string if it had none.
+fn uri_string(uri: Option<ComPtr<Uri>>) -> Result<String, winrt::Error> {
+    match uri {
+        Some(uri) => Ok(uri.get_absolute_uri()?.to_string()),
+        None => Ok(String::new()),
+    }
+}
+
+/// Take a reference of our own to a COM object we have only been lent, as event handlers are.
+unsafe fn add_ref<T>(ptr: *mut T) -> ComPtr<T> {
+    let borrowed = mem::ManuallyDrop::new(ComPtr::wrap(ptr));
+    (*borrowed).clone()
+}
+
+/// Decode the PNG image that `capture_preview_to_stream_async` wrote to the stream, to complete
+/// the capture with.
+fn decode_capture(stream: &InMemoryRandomAccessStream, resolver: capture::Resolver) {
+    let operation = match stream
+        .seek(0)
+        .and_then(|()| BitmapDecoder::create_async(stream))
+    {
+        Ok(operation) => operation,
+        Err(error) => return resolver.resolve(Err(error.into())),
+    };
+    let mut resolver = FakeSend(Some(resolver));
+    let _ = operation.set_completed(&AsyncOperationCompletedHandler::new(
+        move |sender, _status| {
+            if let Some(resolver) = resolver.0.take() {
+                let decoder = unsafe { &mut *sender }.get_results().and_then(
+                    |decoder: Option<ComPtr<BitmapDecoder>>| {
+                        decoder.ok_or(winrt::Error::UnspecifiedFailure)
+                    },
+                );
+                match decoder {
+                    Ok(decoder) => read_pixels(&decoder, resolver),
+                    Err(error) => resolver.resolve(Err(error.into())),
+                }
+            }
+            Ok(())
+        },
+    ));
+}
+
+/// Read the decoded image as straight RGBA, to complete the capture with.
+fn read_pixels(decoder: &ComPtr<BitmapDecoder>, resolver: capture::Resolver) {
+    let started = decoder
+        .query_interface::<IBitmapFrame>()
+        .ok_or(winrt::Error::NoSuchInterface)
+        .and_then(|frame| {
+            let size = (frame.get_pixel_width()?, frame.get_pixel_height()?);
+            let operation = frame.get_pixel_data_transformed_async(
+                BitmapPixelFormat::Rgba8,
+                BitmapAlphaMode::Straight,
+                &BitmapTransform::new(),
+                ExifOrientationMode::IgnoreExifOrientation,
+                ColorManagementMode::DoNotColorManage,
+            )?;
+            Ok((size, operation))
+        });
+    let ((width, height), operation) = match started {
+        Ok(started) => started,
+        Err(error) => return resolver.resolve(Err(error.into())),
+    };
+    let mut resolver = FakeSend(Some(resolver));
+    let _ = operation.set_completed(&AsyncOperationCompletedHandler::new(
+        move |sender, _status| {
+            if let Some(resolver) = resolver.0.take() {
+                let pixels = unsafe { &mut *sender }
+                    .get_results()
+                    .and_then(|provider: Option<ComPtr<PixelDataProvider>>| {
+                        provider.ok_or(winrt::Error::UnspecifiedFailure)
+                    })
+                    .and_then(|provider| provider.detach_pixel_data());
+                resolver.resolve(
+                    pixels
+                        .map(|pixels| Image {
+                            width,
+                            height,
+                            rgba: pixels.to_vec(),
+                        })
+                        .map_err(Into::into),
+                );
+            }
+            Ok(())
+        },
+    ));
+}
+
+/// Completes a deferral when dropped, so that nothing waits forever if we fail along the way.
+struct CompleteOnDrop(ComPtr<Deferral>);
+
+impl Drop for CompleteOnDrop {
+    fn drop(&mut self) {
+        let _ = self.0.complete();
+    }
+}
+
+fn permission_kind(kind: WebViewControlPermissionType) -> PermissionKind {
+    match kind {
+        WebViewControlPermissionType::Geolocation => PermissionKind::Geolocation,
+        WebViewControlPermissionType::Media => PermissionKind::Media,
+        WebViewControlPermissionType::Screen => PermissionKind::ScreenCapture,
+        WebViewControlPermissionType::WebNotifications => PermissionKind::Notifications,
+        WebViewControlPermissionType::PointerLock => PermissionKind::PointerLock,
+        WebViewControlPermissionType::UnlimitedIndexedDBQuota => PermissionKind::Storage,
+        WebViewControlPermissionType::ImmersiveView => {
+            PermissionKind::Other("immersive view".to_owned())
+        }
+        _ => PermissionKind::Other(format!("permission type {}", kind.0)),
+    }
+}
+
+fn respond_to_deferred_permission_request(
+    control: &IWebViewControl,
+    id: u32,
+    allow: bool,
+) -> Result<(), winrt::Error> {
+    let request = control
+        .get_deferred_permission_request_by_id(id)?
+        .ok_or(winrt::Error::InvalidArgument)?;
+    if allow {
+        request.allow()
+    } else {
+        request.deny()
+    }
+}
+
+/// The name and value of each of the request’s headers, in order.
+fn header_pairs(
+    headers: &ComPtr<HttpRequestHeaderCollection>,
+) -> Result<Vec<(String, String)>, winrt::Error> {
+    let iterator = headers
+        .query_interface::<IIterable<IKeyValuePair<HString, HString>>>()
+        .ok_or(winrt::Error::NoSuchInterface)?
+        .first()?
+        .ok_or(winrt::Error::UnspecifiedFailure)?;
+    let mut pairs = Vec::new();
+    while iterator.get_has_current()? {
+        let pair = iterator
+            .get_current()?
+            .ok_or(winrt::Error::UnspecifiedFailure)?;
+        pairs.push((pair.get_key()?.to_string(), pair.get_value()?.to_string()));
+        iterator.move_next()?;
+    }
+    Ok(pairs)
+}
+
+/// Replace all of the request’s headers.
+fn replace_headers(
+    headers: &ComPtr<HttpRequestHeaderCollection>,
+    pairs: &[(String, String)],
+) -> Result<(), winrt::Error> {
+    headers
+        .query_interface::<IMap<HString, HString>>()
+        .ok_or(winrt::Error::NoSuchInterface)?
+        .clear()?;
+    for (name, value) in pairs {
+        headers.try_append_without_validation(
+            &FastHString::from(&**name),
+            &FastHString::from(&**value),
+        )?;
+    }
+    Ok(())
+}
+
+/// Turn a response for a `WebResourceRequest` into one for the control.
+fn http_response(response: SchemeResponse) -> Result<ComPtr<HttpResponseMessage>, winrt::Error> {
+    let message = HttpResponseMessage::create(HttpStatusCode(i32::from(response.status)))?;
+    let body = response
+        .body
+        .into_bytes()
+        .map_err(|_| winrt::Error::UnspecifiedFailure)?;
+    let writer = DataWriter::new();
+    writer.write_bytes(&body)?;
+    let buffer = writer
+        .detach_buffer()?
+        .ok_or(winrt::Error::UnspecifiedFailure)?;
+    let content = HttpBufferContent::create_from_buffer(&buffer)?;
+    let content_headers = content
+        .get_headers()?
+        .ok_or(winrt::Error::UnspecifiedFailure)?;
+    let message_headers = message
+        .get_headers()?
+        .ok_or(winrt::Error::UnspecifiedFailure)?;
+    for (name, value) in &response.headers {
+        let lowercase_name = name.to_ascii_lowercase();
+        let (name, value) = (FastHString::from(&**name), FastHString::from(&**value));
+        // Headers describing the body, like Content-Type, belong to the content, not the message.
+        if lowercase_name.starts_with("content-")
+            || lowercase_name == "expires"
+            || lowercase_name == "last-modified"
+        {
+            content_headers.try_append_without_validation(&name, &value)?;
+        } else {
+            message_headers.try_append_without_validation(&name, &value)?;
+        }
+    }
+    message.set_content(&content)?;
+    Ok(message)
+}
+
+/// WebErrorStatus uses the HTTP status codes for HTTP errors.
+fn http_status(status: WebErrorStatus) -> Option<u16> {
+    if (300..600).contains(&status.0) {
+        Some(status.0 as u16)
+    } else {
+        None
+    }
+}
+
+fn web_error_kind(status: WebErrorStatus) -> WebErrorKind {
+    match status {
+        WebErrorStatus::OperationCanceled => WebErrorKind::Cancelled,
+        WebErrorStatus::HostNameNotResolved => WebErrorKind::HostNotFound,
+        WebErrorStatus::CannotConnect | WebErrorStatus::ServerUnreachable => {
+            WebErrorKind::CannotConnect
+        }
+        WebErrorStatus::ConnectionAborted
+        | WebErrorStatus::ConnectionReset
+        | WebErrorStatus::Disconnected => WebErrorKind::ConnectionLost,
+        WebErrorStatus::Timeout => WebErrorKind::Timeout,
+        WebErrorStatus::CertificateCommonNameIsIncorrect
+        | WebErrorStatus::CertificateExpired
+        | WebErrorStatus::CertificateContainsErrors
+        | WebErrorStatus::CertificateRevoked
+        | WebErrorStatus::CertificateIsInvalid => WebErrorKind::Certificate,
+        _ if http_status(status).is_some() => WebErrorKind::Http,
+        _ => WebErrorKind::Other(format!("{:?}", status)),
+    }
+}
+
 pub fn is_available() -> bool {
-    ApiInformation::is_type_present(&FastHString::from("Windows.Web.UI.Interop.WebViewControl"))
-        .unwrap_or(false)
-    // When we start using AddInitializeScript, which has a higher baseline, switch to this:
-    // ApiInformation::is_method_present(
-    //     &FastHString::from("Windows.Web.UI.Interop.WebViewControl"),
-    //     &FastHString::from("AddInitializeScript"),
-    // ).unwrap_or(false)
+    // AddInitializeScript has a higher baseline than the type itself.
+    ApiInformation::is_method_present(
+        &FastHString::from("Windows.Web.UI.Interop.WebViewControl"),
+        &FastHString::from("AddInitializeScript"),
+    )
+    .unwrap_or(false)
+}
+
+/// `is_available`, failing with the reason if not.
+pub(crate) fn check_available() -> Result<(), Error> {
+    if is_available() {
+        Ok(())
+    } else {
+        Err(Error::BackendUnavailable(
+            "this version of Windows doesn’t have WebViewControl.AddInitializeScript, which \
+             needs build 17763"
+                .to_owned(),
+        ))
+    }
 }
 
 unsafe fn register_host_class() {
@@ -187,23 +513,31 @@ pub struct Process {
 impl Process {
     pub fn new() -> Process {
         let process = WebViewControlProcess::new();
-        process
-            .add_process_exited(&TypedEventHandler::new(move |_proc, _result| {
-                eprintln!("WebViewControlProcess exited, should we do anything about it?");
-                Ok(())
-            }))
-            .unwrap();
+        // This handler is only for diagnostics, so failing to add it is no reason to fail.
+        let _ = process.add_process_exited(&TypedEventHandler::new(move |_proc, _result| {
+            eprintln!("WebViewControlProcess exited, should we do anything about it?");
+            Ok(())
+        }));
 
         Process { process }
     }
 
+    /// Start creating a control.
+    ///
+    /// Creation is asynchronous. Operations on the returned control are queued until it completes,
+    /// and then replayed in order. Use `Control::ready()` to find out when that has happened, or
+    /// whether it failed.
+    ///
+    /// Of the settings, only `Setting::JavaScript`, `Setting::ScriptNotify` and
+    /// `Setting::IndexedDb` are supported.
     pub fn create_control(
         &self,
         hwnd_type: HwndType,
         position: (i32, i32),
         size: (i32, i32),
-        callback: Option<impl FnOnce(Control) + 'static>,
+        settings: &ControlSettings,
     ) -> Result<Control, Error> {
+        check_available()?;
         let hwnd = match hwnd_type {
             HwndType::FillWindow(hwnd) => hwnd,
             HwndType::ConsumeHwnd(hwnd) => hwnd,
@@ -228,61 +562,134 @@ impl Process {
                     _ => false,
                 },
                 control: None,
-                queued_bounds_update: None,
-                queued_focus: false,
+                readiness: Readiness::pending(),
+                queued_operations: Vec::new(),
+                schemes: HashMap::new(),
+                zoom: 1.0,
+                background_color: Color::WHITE,
+                zoom_changed_handlers: Vec::new(),
+                next_handler_id: 0,
+                unsupported_settings: settings.unsupported(SUPPORTED_SETTINGS),
             })),
         };
 
+        // Queued first, so that the settings are in place before anything else happens.
+        let settings = settings.clone();
+        control
+            .run_or_queue(move |control| control.apply_settings(&settings).map_err(Error::from))?;
+
         // I believe AsyncOperationCompletedHandler should simply not require Send, but it does for
         // now. So, time to pretend Send with this menace.
         let mut control2 = FakeSend(control.clone());
-        let mut callback = FakeSend(callback);
-        operation
-            .set_completed(&AsyncOperationCompletedHandler::new(
-                move |sender, _args| {
-                    // When it doesn’t require Send, the following four lines should reduce to this:
-                    // control = operation.get_results().unwrap();
-                    let web_view_control = unsafe { &mut *sender }.get_results().unwrap();
-                    control2.0.control_created(web_view_control);
-                    if let Some(callback) = callback.0.take() {
-                        // XXX: unnecessary clone here, because this closure is FnMut rather than
-                        // FnOnce as it could in theory safely be.
-                        callback(control2.0.clone());
-                    }
-                    Ok(())
-                },
-            ))
-            .unwrap();
+        operation.set_completed(&AsyncOperationCompletedHandler::new(
+            move |sender, _args| {
+                // When it doesn’t require Send, the following two lines should reduce to this:
+                // control.control_created(operation.get_results());
+                let web_view_control = unsafe { &mut *sender }.get_results();
+                control2.0.control_created(web_view_control);
+                Ok(())
+            },
+        ))?;
 
         Ok(control)
     }
+
+    /// Create a control in a new HWND inside any window that can provide its raw handle, which
+    /// must be a Win32 window; as with `HwndType::NewHwndInWindow`.
+    pub fn create_control_in_window<W: HasRawWindowHandle>(
+        &self,
+        parent: &W,
+        position: (i32, i32),
+        size: (i32, i32),
+        settings: &ControlSettings,
+    ) -> Result<Control, Error> {
+        self.create_control_in_raw_window(&parent.raw_window_handle(), position, size, settings)
+    }
+
+    pub(crate) fn create_control_in_raw_window(
+        &self,
+        handle: &RawWindowHandle,
+        position: (i32, i32),
+        size: (i32, i32),
+        settings: &ControlSettings,
+    ) -> Result<Control, Error> {
+        match handle {
+            RawWindowHandle::Windows(ref handle) if !handle.hwnd.is_null() => self.create_control(
+                HwndType::NewHwndInWindow(handle.hwnd as HWND),
+                position,
+                size,
+                settings,
+            ),
+            _ => Err(Error::InvalidParent(
+                "EdgeHTML can only be put in Win32 windows".to_owned(),
+            )),
+        }
+    }
 }
 
-// A better solution would probably involve futures and Pin.
-// Then we could hopefully do away with the Rc<RefCell<_>> wrapping.
+// Rc<RefCell<_>> because the CreateWebViewControlAsync completed handler needs to share it.
 #[derive(Clone)]
 pub struct Control {
     inner: Rc<RefCell<ControlInner>>,
 }
 
+/// An operation issued before the control was created, to be run once it has been.
+type QueuedOperation = Box<dyn FnOnce(&EdgeWebViewControl) -> Result<(), Error>>;
+
+/// Event handlers, each with an id, so that its subscription can remove it.
+type Handlers<H> = Vec<(usize, Rc<RefCell<H>>)>;
+
 pub struct ControlInner {
     hwnd: HWND,
     is_window_hwnd: bool,
 
     // Option because it’s async.
     control: Option<ComPtr<WebViewControl>>,
+    readiness: Readiness,
 
-    // Certain operations may be queued while the control is loading. For example, handling resize.
-    queued_bounds_update: Option<Rect>,
-    queued_focus: bool,
+    // Operations issued while the control is loading, in the order they were issued.
+    queued_operations: Vec<QueuedOperation>,
+
+    // Custom schemes, by lowercase name. Navigating to one of these goes to a local stream URI.
+    schemes: HashMap<String, ComPtr<IUriToStreamResolver>>,
+
+    // The zoom factor as last set, which the control doesn’t report changes to, and so we raise
+    // ZoomChanged ourselves, each handler with an id for its subscription to remove it by. Rc so
+    // that they can be called without holding a borrow on the ControlInner.
+    zoom: f64,
+    zoom_changed_handlers: Handlers<dyn FnMut(f64)>,
+    next_handler_id: usize,
+
+    // The default background colour as last set, so that it can be reported while the control is
+    // still loading.
+    background_color: Color,
+
+    unsupported_settings: Vec<Setting>,
 }
 
 impl ControlInner {
+    /// Run the operation now if the control has been created, or queue it to be run once it has.
+    ///
+    /// Returns an error if creation has already failed, as the operation can never happen.
+    fn run_or_queue<F>(&mut self, operation: F) -> Result<(), Error>
+    where
+        F: FnOnce(&EdgeWebViewControl) -> Result<(), Error> + 'static,
+    {
+        match self.control {
+            Some(ref control) => operation(&EdgeWebViewControl {
+                control: control.clone(),
+            }),
+            None if self.readiness.has_failed() => Err(Error::CreationFailed),
+            None => {
+                self.queued_operations.push(Box::new(operation));
+                Ok(())
+            }
+        }
+    }
+
     /// Updates the WebViewControl’s bounds based on the HWND’s current values.
     /// Returns an error if it fails to get the window rect, which I think shouldn’t ever happen.
-    /// Currently returns success if the control is simply not ready yet.
-    /// TODO: revisit that decision, taking into account also what happens if the window is resized
-    /// while the control is girding its loins.
+    /// If the control is not ready yet, the update is queued.
     fn update_bounds(&mut self) -> Result<(), Error> {
         let mut rect = RECT {
             top: 0,
@@ -310,46 +717,117 @@ impl ControlInner {
     }
 
     fn update_bounds_from_rect(&mut self, rect: Rect) -> Result<(), Error> {
-        println!("Updating bounds to {:?}", rect);
-        if let Some(ref control) = self.control {
-            let control_site = control.query_interface::<IWebViewControlSite>().unwrap();
+        self.run_or_queue(move |control| {
+            let control_site = control
+                .control
+                .query_interface::<IWebViewControlSite>()
+                .ok_or(winrt::Error::NoSuchInterface)?;
             control_site.set_bounds(rect)?;
-        } else {
-            self.queued_bounds_update = Some(rect);
-        }
-        Ok(())
+            Ok(())
+        })
     }
 
     fn focus(&mut self) -> Result<(), Error> {
-        if let Some(ref control) = self.control {
-            let control_site = control.query_interface::<IWebViewControlSite>().unwrap();
-            control_site.move_focus(WebViewControlMoveFocusReason::Programmatic)?;
-        } else {
-            self.queued_focus = true;
-        }
-        Ok(())
+        self.run_or_queue(|control| control.move_focus().map_err(Error::from))
     }
 }
 
 impl Control {
     // For internal use, part of the CreateWebViewControlAsync completed handler.
-    fn control_created(&mut self, web_view_control: Option<ComPtr<WebViewControl>>) {
-        let mut inner = self.inner.borrow_mut();
-        inner.control = web_view_control;
-        if let Some(rect) = inner.queued_bounds_update {
-            inner.queued_bounds_update = None;
-            // There’s nothing we can do if this fails; maybe better to be silent like this?
-            let _ = inner.update_bounds_from_rect(rect);
+    //
+    // Replays the queued operations in order. If any of them fail, the rest are still run, and the
+    // first error is reported through `ready()`. If creation failed, the queued operations are
+    // discarded.
+    fn control_created(
+        &mut self,
+        web_view_control: Result<Option<ComPtr<WebViewControl>>, winrt::Error>,
+    ) {
+        let (control, queued_operations) = {
+            let mut inner = self.inner.borrow_mut();
+            let queued_operations = mem::take(&mut inner.queued_operations);
+            let control = match web_view_control {
+                Ok(Some(control)) => control,
+                Ok(None) => return inner.readiness.set_failed(Error::CreationFailed),
+                Err(error) => return inner.readiness.set_failed(error.into()),
+            };
+            inner.control = Some(control.clone());
+            (EdgeWebViewControl { control }, queued_operations)
+        };
+        let mut first_error = None;
+        for operation in queued_operations {
+            if let Err(error) = operation(&control) {
+                first_error.get_or_insert(error);
+            }
+        }
+        self.inner.borrow_mut().readiness.set_created(first_error);
+    }
+
+    /// Returns a future that resolves to this control once it has been created.
+    ///
+    /// It resolves to an error if creation failed, or if replaying an operation queued during
+    /// creation failed (in which case the control is still usable). Only the first future to
+    /// resolve gets that error; later ones get `Error::CreationFailed` or success.
+    ///
+    /// The future must be polled on the thread the control was created on, so use a
+    /// single-threaded executor pumped from your UI thread.
+    pub fn ready(&self) -> Ready {
+        Ready {
+            control: self.clone(),
         }
-        if inner.queued_focus {
-            let _ = inner.focus();
+    }
+
+    /// Run the operation now if the control has been created, or queue it to be run once it has.
+    ///
+    /// Either way it runs without a borrow on the ControlInner, so it may raise our own events.
+    fn run_or_queue<F>(&self, operation: F) -> Result<(), Error>
+    where
+        F: FnOnce(&EdgeWebViewControl) -> Result<(), Error> + 'static,
+    {
+        let control = self.inner.borrow().control.clone();
+        match control {
+            Some(control) => operation(&EdgeWebViewControl { control }),
+            None => self.inner.borrow_mut().run_or_queue(operation),
         }
     }
 
+    /// Add an event handler with `add` once the control is ready.
+    ///
+    /// Dropping the returned subscription removes the handler; if it hasn’t been added yet, the
+    /// removal is queued after it.
+    fn subscribe<F>(&self, add: F) -> Result<Subscription, Error>
+    where
+        F: FnOnce(&EdgeWebViewControl) -> Result<Subscription, winrt::Error> + 'static,
+    {
+        let added = Rc::new(RefCell::new(None));
+        let added2 = added.clone();
+        self.run_or_queue(move |control| {
+            *added2.borrow_mut() = Some(add(control)?);
+            Ok(())
+        })?;
+        let inner = Rc::downgrade(&self.inner);
+        Ok(Subscription::new(move || {
+            // If the control is gone, dropping `added` is all there is to do.
+            if let Some(inner) = inner.upgrade() {
+                let _ = inner.borrow_mut().run_or_queue(move |_control| {
+                    added.borrow_mut().take();
+                    Ok(())
+                });
+            }
+        }))
+    }
+
     pub fn focus(&self) -> Result<(), Error> {
         self.inner.borrow_mut().focus()
     }
 
+    /// Add a handler for when the control gains focus (`true`) or loses it (`false`).
+    pub fn add_focus_changed<F>(&self, f: F) -> Result<Subscription, Error>
+    where
+        F: FnMut(bool) + 'static,
+    {
+        self.subscribe(move |control| control.add_focus_changed(f))
+    }
+
     pub fn resize(
         &self,
         position: Option<(i32, i32)>,
@@ -400,21 +878,343 @@ impl Control {
     pub fn get_inner(&self) -> Option<ComPtr<WebViewControl>> {
         self.inner.borrow().control.clone()
     }
+
+    /// Get a wrapper around the underlying WebViewControl exposing more of its functionality.
+    ///
+    /// Returns None if the control hasn’t been created yet.
+    pub fn get_edge_control(&self) -> Option<EdgeWebViewControl> {
+        self.get_inner()
+            .map(|control| EdgeWebViewControl { control })
+    }
 }
 
-pub trait WebView {
-    type Error;
-    fn navigate(&self, url: &str) -> Result<(), Self::Error>;
+/// A future that resolves once a control has been created. See `Control::ready`.
+pub struct Ready {
+    control: Control,
+}
+
+impl Future for Ready {
+    type Output = Result<Control, Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<Control, Error>> {
+        let control = &self.control;
+        control
+            .inner
+            .borrow_mut()
+            .readiness
+            .poll(cx)
+            .map(|result| result.map(|()| control.clone()))
+    }
 }
 
 impl WebView for Control {
-    type Error = winrt::Error;
-    fn navigate(&self, url: &str) -> Result<(), winrt::Error> {
-        if let Some(ref control) = self.inner.borrow().control {
-            control.navigate(&*Uri::create_uri(&FastHString::from(&*url))?)?;
+    fn navigate(&self, url: &str) -> Result<(), Error> {
+        scheme::check_url(url)?;
+        if let Some((scheme, rest)) = scheme::split_url(url) {
+            let resolver = self
+                .inner
+                .borrow()
+                .schemes
+                .get(&scheme.to_ascii_lowercase())
+                .cloned();
+            if let Some(resolver) = resolver {
+                let content_identifier = scheme.to_ascii_lowercase();
+                let path = scheme::path_of(rest).to_owned();
+                return self.run_or_queue(move |control| {
+                    let uri = control.build_local_stream_uri(&content_identifier, &path)?;
+                    control.navigate_to_local_stream_uri(&uri, &resolver)?;
+                    Ok(())
+                });
+            }
+        }
+        let url = url.to_owned();
+        self.run_or_queue(move |control| {
+            control.navigate(&url).map_err(|error| match error {
+                winrt::Error::InvalidArgument => Error::InvalidUrl(url.clone()),
+                error => error.into(),
+            })
+        })
+    }
+
+    fn navigate_to_string(&self, html: &str) -> Result<(), Error> {
+        let html = html.to_owned();
+        self.run_or_queue(move |control| control.navigate_to_string(&html).map_err(Error::from))
+    }
+
+    fn go_back(&self) -> Result<(), Error> {
+        self.run_or_queue(|control| control.go_back().map_err(Error::from))
+    }
+
+    fn go_forward(&self) -> Result<(), Error> {
+        self.run_or_queue(|control| control.go_forward().map_err(Error::from))
+    }
+
+    fn reload(&self) -> Result<(), Error> {
+        self.run_or_queue(|control| control.refresh().map_err(Error::from))
+    }
+
+    fn stop(&self) -> Result<(), Error> {
+        self.run_or_queue(|control| control.stop().map_err(Error::from))
+    }
+
+    fn can_go_back(&self) -> bool {
+        self.get_edge_control()
+            .is_some_and(|control| control.can_go_back())
+    }
+
+    fn can_go_forward(&self) -> bool {
+        self.get_edge_control()
+            .is_some_and(|control| control.can_go_forward())
+    }
+
+    fn url(&self) -> Option<String> {
+        self.get_edge_control().and_then(|control| control.source())
+    }
+
+    fn title(&self) -> String {
+        self.get_edge_control()
+            .map(|control| control.document_title())
+            .unwrap_or_default()
+    }
+
+    fn unsupported_settings(&self) -> Vec<Setting> {
+        self.inner.borrow().unsupported_settings.clone()
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        CAPABILITIES
+    }
+
+    fn zoom(&self) -> f64 {
+        self.inner.borrow().zoom
+    }
+
+    /// This sets the scale of the control, which is what zooming it amounts to.
+    fn set_zoom(&self, zoom: f64) -> Result<(), Error> {
+        zoom::check_zoom(zoom)?;
+        self.inner.borrow_mut().zoom = zoom;
+        // ZoomChanged is raised once the zoom has actually been applied, not while it’s queued.
+        let inner = Rc::downgrade(&self.inner);
+        self.run_or_queue(move |control| {
+            control.set_scale(zoom)?;
+            if let Some(inner) = inner.upgrade() {
+                let handlers: Vec<_> = inner
+                    .borrow()
+                    .zoom_changed_handlers
+                    .iter()
+                    .map(|(_id, handler)| handler.clone())
+                    .collect();
+                for handler in handlers {
+                    (*handler.borrow_mut())(zoom);
+                }
+            }
+            Ok(())
+        })
+    }
+
+    /// The handler is only called for `set_zoom`, as the control doesn’t report zooming by the
+    /// user.
+    fn add_zoom_changed<F>(&self, f: F) -> Result<Subscription, Error>
+    where
+        F: FnMut(f64) + 'static,
+    {
+        let id = {
+            let mut inner = self.inner.borrow_mut();
+            inner.next_handler_id += 1;
+            let id = inner.next_handler_id;
+            inner
+                .zoom_changed_handlers
+                .push((id, Rc::new(RefCell::new(f))));
+            id
+        };
+        let inner = Rc::downgrade(&self.inner);
+        Ok(Subscription::new(move || {
+            if let Some(inner) = inner.upgrade() {
+                inner
+                    .borrow_mut()
+                    .zoom_changed_handlers
+                    .retain(|&(handler_id, _)| handler_id != id);
+            }
+        }))
+    }
+
+    fn default_background_color(&self) -> Color {
+        self.inner.borrow().background_color
+    }
+
+    fn set_default_background_color(&self, color: Color) -> Result<(), Error> {
+        self.inner.borrow_mut().background_color = color;
+        self.run_or_queue(move |control| {
+            control
+                .set_default_background_color(color)
+                .map_err(Error::from)
+        })
+    }
+
+    fn run_script(&self, script: &str) -> Result<(), Error> {
+        let script = script.to_owned();
+        self.run_or_queue(move |control| {
+            control.invoke_script_async("eval", &[&script])?;
+            Ok(())
+        })
+    }
+
+    fn eval<T: DeserializeOwned>(&self, script: &str) -> Eval<T> {
+        let (resolver, eval) = script::channel();
+        let script = script::wrap(script);
+        // If the operation is never run because creation failed, dropping the resolver takes care
+        // of the eval; likewise if the completed handler is dropped without being called.
+        let result = self.run_or_queue(move |control| {
+            let operation = match control.invoke_script_async("eval", &[&script]) {
+                Ok(operation) => operation,
+                Err(error) => {
+                    resolver.resolve(Err(error.into()));
+                    return Ok(());
+                }
+            };
+            let mut resolver = FakeSend(Some(resolver));
+            operation.set_completed(&AsyncOperationCompletedHandler::new(
+                move |sender, _args| {
+                    if let Some(resolver) = resolver.0.take() {
+                        let outcome = unsafe { &mut *sender }.get_results();
+                        resolver.resolve_outcome(
+                            outcome
+                                .map(|outcome: HString| outcome.to_string())
+                                .map_err(Into::into),
+                        );
+                    }
+                    Ok(())
+                },
+            ))?;
+            Ok(())
+        });
+        match result {
+            Ok(()) => eval,
+            Err(error) => Eval::failed(error),
+        }
+    }
+
+    fn capture_image(&self, clip: Option<capture::Rect>) -> Capture {
+        let (resolver, capture) = capture::channel(clip);
+        // As with eval, if the resolver is dropped along the way, that takes care of the capture.
+        let result = self.run_or_queue(move |control| {
+            let stream = InMemoryRandomAccessStream::new();
+            let action = match control.capture_preview_to_stream_async(&stream) {
+                Ok(action) => action,
+                Err(error) => {
+                    resolver.resolve(Err(error.into()));
+                    return Ok(());
+                }
+            };
+            let mut resolver = FakeSend(Some(resolver));
+            let stream = FakeSend(stream);
+            action.set_completed(&AsyncActionCompletedHandler::new(move |sender, _status| {
+                if let Some(resolver) = resolver.0.take() {
+                    match unsafe { &*sender }.get_results() {
+                        Ok(()) => decode_capture(&stream.0, resolver),
+                        Err(error) => resolver.resolve(Err(error.into())),
+                    }
+                }
+                Ok(())
+            }))?;
+            Ok(())
+        });
+        match result {
+            Ok(()) => capture,
+            Err(error) => Capture::failed(error),
         }
+    }
+
+    /// The WebViewControl has no way of printing, so this always fails.
+    fn print(&self) -> Result<(), Error> {
+        self.capabilities().require(Capability::Printing)
+    }
+
+    /// EdgeHTML can only do this with local stream URIs; see the `scheme` module for what that
+    /// means. Only navigating the control with `navigate` makes use of the scheme.
+    fn register_scheme<F>(&self, scheme: &str, handler: F) -> Result<(), Error>
+    where
+        F: Fn(&SchemeRequest) -> SchemeResponse + 'static,
+    {
+        let resolver = edge_scheme::stream_resolver(Rc::new(handler));
+        self.inner
+            .borrow_mut()
+            .schemes
+            .insert(scheme.to_ascii_lowercase(), resolver);
         Ok(())
     }
+
+    /// EdgeHTML has no way of removing an initialization script, so removal only works if the
+    /// control hasn’t finished creation yet; after that, the script stays for the life of the
+    /// control.
+    fn add_init_script(&self, source: &str) -> Result<Subscription, Error> {
+        let source = source.to_owned();
+        let removed = Rc::new(Cell::new(false));
+        let removed2 = removed.clone();
+        self.run_or_queue(move |control| {
+            if removed2.get() {
+                return Ok(());
+            }
+            control.add_initialize_script(&source).map_err(Error::from)
+        })?;
+        Ok(Subscription::new(move || removed.set(true)))
+    }
+
+    fn add_script_notify<F>(&self, f: F) -> Result<Subscription, Error>
+    where
+        F: FnMut(String) + 'static,
+    {
+        self.subscribe(move |control| control.add_script_notify(f))
+    }
+
+    fn add_navigation_starting<F>(&self, f: F) -> Result<Subscription, Error>
+    where
+        F: FnMut(&NavigationStarting) + 'static,
+    {
+        self.subscribe(move |control| control.add_navigation_starting(f))
+    }
+
+    fn add_content_loading<F>(&self, f: F) -> Result<Subscription, Error>
+    where
+        F: FnMut(&ContentLoading) + 'static,
+    {
+        self.subscribe(move |control| control.add_content_loading(f))
+    }
+
+    fn add_dom_content_loaded<F>(&self, f: F) -> Result<Subscription, Error>
+    where
+        F: FnMut(&DomContentLoaded) + 'static,
+    {
+        self.subscribe(move |control| control.add_dom_content_loaded(f))
+    }
+
+    fn add_navigation_completed<F>(&self, f: F) -> Result<Subscription, Error>
+    where
+        F: FnMut(&NavigationCompleted) + 'static,
+    {
+        self.subscribe(move |control| control.add_navigation_completed(f))
+    }
+
+    fn add_new_window_requested<F>(&self, f: F) -> Result<Subscription, Error>
+    where
+        F: FnMut(&NewWindowRequested) + 'static,
+    {
+        self.subscribe(move |control| control.add_new_window_requested(f))
+    }
+
+    fn add_permission_requested<F>(&self, f: F) -> Result<Subscription, Error>
+    where
+        F: FnMut(&PermissionRequest) + 'static,
+    {
+        self.subscribe(move |control| control.add_permission_requested(f))
+    }
+
+    fn add_web_resource_requested<F>(&self, f: F) -> Result<Subscription, Error>
+    where
+        F: FnMut(&WebResourceRequest) + 'static,
+    {
+        self.subscribe(move |control| control.add_web_resource_requested(f))
+    }
 }
 
 pub struct EdgeWebViewControl {
@@ -444,13 +1244,37 @@ impl EdgeWebViewControl {
             .unwrap_or(false)
     }
 
-    // pub fn default_background_color(&self) {
-    //     self.control.get_default_background_color()
-    // }
+    /// Retrieves the URL of the current page.
+    ///
+    /// Returns None if the control is not functioning.
+    pub fn source(&self) -> Option<String> {
+        self.control
+            .get_source()
+            .ok()
+            .and_then(|uri| uri)
+            .and_then(|uri| uri.get_absolute_uri().ok())
+            .map(|s| s.to_string())
+    }
 
-    // pub fn set_default_background_color(&self) {
-    //     self.control.set_default_background_color()
-    // }
+    /// The colour painted behind the page where it doesn’t paint its own background.
+    ///
+    /// Returns white if the control is not functioning.
+    pub fn default_background_color(&self) -> Color {
+        self.control
+            .get_default_background_color()
+            .map(|color| Color::rgba(color.R, color.G, color.B, color.A))
+            .unwrap_or(Color::WHITE)
+    }
+
+    /// Set the colour painted behind the page where it doesn’t paint its own background.
+    pub fn set_default_background_color(&self, color: Color) -> Result<(), winrt::Error> {
+        self.control.set_default_background_color(WinRtColor {
+            A: color.a,
+            R: color.r,
+            G: color.g,
+            B: color.b,
+        })
+    }
 
     // pub fn deferred_permission_requests(&self) {
     //     self.control.get_deferred_permission_requests()
@@ -466,52 +1290,316 @@ impl EdgeWebViewControl {
             .unwrap_or(String::new())
     }
 
-    // /// Sets the zoom factor for the contents of the control.
-    // /// Returns 1.0 if the control is not functioning.
-    // pub fn scale(&self) -> f64 {
-    //     self.control.get_scale()
-    // }
+    /// The zoom factor for the contents of the control.
+    ///
+    /// Returns 1.0 if the control is not functioning.
+    pub fn scale(&self) -> f64 {
+        self.control
+            .query_interface::<IWebViewControlSite>()
+            .and_then(|control_site| control_site.get_scale().ok())
+            .unwrap_or(1.0)
+    }
+
+    /// Set the zoom factor for the contents of the control.
+    pub fn set_scale(&self, scale: f64) -> Result<(), winrt::Error> {
+        self.control
+            .query_interface::<IWebViewControlSite>()
+            .ok_or(winrt::Error::NoSuchInterface)?
+            .set_scale(scale)
+    }
 
     // Skipped properties:
     //
     // • Bounds, because we manage that otherwise.
     // • IsVisible, purely because I can’t think why that exists yet.
     // • Process, because we don’t *want* to expose that cycle.
-    // • Settings, because we’ll expose these otherwise, if ever.
+    // • Settings, which are exposed through `apply_settings` instead.
 
     // --- Methods ---
 
-    // pub fn add_initialize_script(&self, script: &str) {
-    //     self.control.add_initialize_script(script.into())
-    // }
+    /// Apply those of the settings that EdgeHTML supports: JavaScript, script notify and
+    /// IndexedDB. The rest are left alone.
+    pub fn apply_settings(&self, settings: &ControlSettings) -> Result<(), winrt::Error> {
+        let control_settings = self
+            .control
+            .get_settings()?
+            .ok_or(winrt::Error::UnspecifiedFailure)?;
+        if let Some(enabled) = settings.javascript_enabled {
+            control_settings.set_is_javascript_enabled(enabled)?;
+        }
+        if let Some(allowed) = settings.script_notify_allowed {
+            control_settings.set_is_script_notify_allowed(allowed)?;
+        }
+        if let Some(enabled) = settings.indexed_db_enabled {
+            control_settings.set_is_indexeddb_enabled(enabled)?;
+        }
+        Ok(())
+    }
+
+    /// Add a script to run at the start of each document, before its own scripts. There is no
+    /// way of removing it.
+    pub fn add_initialize_script(&self, script: &str) -> Result<(), winrt::Error> {
+        self.control
+            .query_interface::<IWebViewControl2>()
+            .ok_or(winrt::Error::NoSuchInterface)?
+            .add_initialize_script(&FastHString::from(script))
+    }
+
+    /// Build a URI for `navigate_to_local_stream_uri`. The content identifier becomes the root of
+    /// the URI, and the relative path its path.
+    pub fn build_local_stream_uri(
+        &self,
+        content_identifier: &str,
+        relative_path: &str,
+    ) -> Result<ComPtr<Uri>, winrt::Error> {
+        self.control
+            .build_local_stream_uri(
+                &FastHString::from(content_identifier),
+                &FastHString::from(relative_path),
+            )?
+            .ok_or(winrt::Error::UnspecifiedFailure)
+    }
+
+    /// Write a PNG image of the control’s contents to the stream.
+    pub fn capture_preview_to_stream_async(
+        &self,
+        stream: &IRandomAccessStream,
+    ) -> Result<ComPtr<IAsyncAction>, winrt::Error> {
+        self.control.capture_preview_to_stream_async(stream)
+    }
+
+    /// Copy the selected content, in the formats the engine offers, into a data package.
+    pub fn capture_selected_content_to_data_package_async(
+        &self,
+    ) -> Result<ComPtr<IAsyncOperation<DataPackage>>, winrt::Error> {
+        self.control
+            .capture_selected_content_to_data_package_async()
+    }
+
+    /// Close the control, ending its part in the process. It can’t be used after this.
+    pub fn close(&self) -> Result<(), winrt::Error> {
+        self.control
+            .query_interface::<IWebViewControlSite>()
+            .ok_or(winrt::Error::NoSuchInterface)?
+            .close()
+    }
+
+    /// Get a permission request that was deferred, by its id, in order to answer it.
+    pub fn get_deferred_permission_request_by_id(
+        &self,
+        id: u32,
+    ) -> Result<Option<ComPtr<WebViewControlDeferredPermissionRequest>>, winrt::Error> {
+        self.control.get_deferred_permission_request_by_id(id)
+    }
+
+    pub fn go_back(&self) -> Result<(), winrt::Error> {
+        self.control.go_back()
+    }
+
+    pub fn go_forward(&self) -> Result<(), winrt::Error> {
+        self.control.go_forward()
+    }
+
+    /// Invoke a global function in the page by name, with string arguments.
+    ///
+    /// The operation completes with the function’s return value converted to a string.
+    pub fn invoke_script_async(
+        &self,
+        name: &str,
+        arguments: &[&str],
+    ) -> Result<ComPtr<IAsyncOperation<HString>>, winrt::Error> {
+        self.control
+            .invoke_script_async(&FastHString::from(name), &*string_iterable(arguments)?)
+    }
 
-    // pub fn build_local_stream_uri(&self) {}
-
-    // /// The building block for taking a screenshot of the control.
-    // pub fn capture_preview_to_stream_async(&self) {}
-
-    pub fn capture_selected_content_to_data_package_async(&self) {}
-    pub fn close(&self) {}
-    pub fn get_deferred_permission_request_by_id(&self) {}
-    pub fn go_back(&self) {}
-    pub fn go_forward(&self) {}
-    pub fn invoke_script_async(&self) {}
-    pub fn move_focus(&self) {}
-    pub fn navigate(&self) {}
-    pub fn navigate_to_local_stream_uri(&self) {}
-    pub fn navigate_to_string(&self) {}
-    pub fn navigate_with_http_request_message(&self) {}
-    pub fn refresh(&self) {}
-    pub fn stop(&self) {}
+    /// Move focus into the control.
+    pub fn move_focus(&self) -> Result<(), winrt::Error> {
+        let control_site = self
+            .control
+            .query_interface::<IWebViewControlSite>()
+            .ok_or(winrt::Error::NoSuchInterface)?;
+        control_site.move_focus(WebViewControlMoveFocusReason::Programmatic)
+    }
+
+    /// Add a handler for GotFocus (`true`) and LostFocus (`false`).
+    pub fn add_focus_changed<F>(&self, f: F) -> Result<Subscription, winrt::Error>
+    where
+        F: FnMut(bool) + 'static,
+    {
+        let control_site = self
+            .control
+            .query_interface::<IWebViewControlSite2>()
+            .ok_or(winrt::Error::NoSuchInterface)?;
+        let f = Rc::new(RefCell::new(f));
+        let handler = |focused| {
+            let f = FakeSend(f.clone());
+            TypedEventHandler::new(move |_sender, _args| {
+                (f.0.borrow_mut())(focused);
+                Ok(())
+            })
+        };
+        let got_token = control_site.add_got_focus(&handler(true))?;
+        let lost_token = match control_site.add_lost_focus(&handler(false)) {
+            Ok(token) => token,
+            Err(error) => {
+                let _ = control_site.remove_got_focus(got_token);
+                return Err(error);
+            }
+        };
+        Ok(Subscription::new(move || {
+            let _ = control_site.remove_got_focus(got_token);
+            let _ = control_site.remove_lost_focus(lost_token);
+        }))
+    }
+
+    pub fn navigate(&self, url: &str) -> Result<(), winrt::Error> {
+        self.control
+            .navigate(&*Uri::create_uri(&FastHString::from(url))?)
+    }
+
+    /// Navigate to a URI from `build_local_stream_uri`, with the resolver providing the content for
+    /// it and anything relative to it.
+    pub fn navigate_to_local_stream_uri(
+        &self,
+        uri: &Uri,
+        resolver: &IUriToStreamResolver,
+    ) -> Result<(), winrt::Error> {
+        self.control.navigate_to_local_stream_uri(uri, resolver)
+    }
+
+    /// Load the given HTML as the document.
+    pub fn navigate_to_string(&self, html: &str) -> Result<(), winrt::Error> {
+        self.control.navigate_to_string(&FastHString::from(html))
+    }
+
+    /// Navigate with a request of one’s own making, e.g. to POST or to send other headers.
+    pub fn navigate_with_http_request_message(
+        &self,
+        request: &HttpRequestMessage,
+    ) -> Result<(), winrt::Error> {
+        self.control.navigate_with_http_request_message(request)
+    }
+
+    pub fn refresh(&self) -> Result<(), winrt::Error> {
+        self.control.refresh()
+    }
+
+    pub fn stop(&self) -> Result<(), winrt::Error> {
+        self.control.stop()
+    }
 
     // --- Events ---
 
-    // Skipped: various events to do with loading. If you really need them, take the control ComPtr
-    // and do it yourself.
+    // Skipped: the frame loading events (FrameNavigationStarting and so on), as we only deal in
+    // the top-level document.
+
+    pub fn add_navigation_starting<F>(&self, f: F) -> Result<Subscription, winrt::Error>
+    where
+        F: FnMut(&NavigationStarting) + 'static,
+    {
+        let mut f = FakeSend(f);
+        let token = self
+            .control
+            .add_navigation_starting(&TypedEventHandler::new(
+                move |_sender, args: *mut WebViewControlNavigationStartingEventArgs| {
+                    let args = unsafe { &mut *args };
+                    // EdgeHTML doesn’t say whether it’s a redirect.
+                    let event = NavigationStarting::new(uri_string(args.get_uri()?)?, None);
+                    f.0(&event);
+                    if event.is_cancelled() {
+                        args.set_cancel(true)?;
+                    }
+                    Ok(())
+                },
+            ))?;
+        let control = self.control.clone();
+        Ok(Subscription::new(move || {
+            let _ = control.remove_navigation_starting(token);
+        }))
+    }
+
+    pub fn add_content_loading<F>(&self, f: F) -> Result<Subscription, winrt::Error>
+    where
+        F: FnMut(&ContentLoading) + 'static,
+    {
+        let mut f = FakeSend(f);
+        let token = self.control.add_content_loading(&TypedEventHandler::new(
+            move |_sender, args: *mut WebViewControlContentLoadingEventArgs| {
+                let args = unsafe { &mut *args };
+                f.0(&ContentLoading {
+                    url: uri_string(args.get_uri()?)?,
+                });
+                Ok(())
+            },
+        ))?;
+        let control = self.control.clone();
+        Ok(Subscription::new(move || {
+            let _ = control.remove_content_loading(token);
+        }))
+    }
+
+    pub fn add_dom_content_loaded<F>(&self, f: F) -> Result<Subscription, winrt::Error>
+    where
+        F: FnMut(&DomContentLoaded) + 'static,
+    {
+        let mut f = FakeSend(f);
+        let token = self
+            .control
+            .add_dom_content_loaded(&TypedEventHandler::new(
+                move |_sender, args: *mut WebViewControlDOMContentLoadedEventArgs| {
+                    let args = unsafe { &mut *args };
+                    f.0(&DomContentLoaded {
+                        url: uri_string(args.get_uri()?)?,
+                    });
+                    Ok(())
+                },
+            ))?;
+        let control = self.control.clone();
+        Ok(Subscription::new(move || {
+            let _ = control.remove_dom_content_loaded(token);
+        }))
+    }
+
+    pub fn add_navigation_completed<F>(&self, f: F) -> Result<Subscription, winrt::Error>
+    where
+        F: FnMut(&NavigationCompleted) + 'static,
+    {
+        let mut f = FakeSend(f);
+        let token = self
+            .control
+            .add_navigation_completed(&TypedEventHandler::new(
+                move |_sender, args: *mut WebViewControlNavigationCompletedEventArgs| {
+                    let args = unsafe { &mut *args };
+                    let url = uri_string(args.get_uri()?)?;
+                    let event = if args.get_is_success()? {
+                        NavigationCompleted {
+                            url,
+                            is_success: true,
+                            http_status: None,
+                            error: None,
+                        }
+                    } else {
+                        let status = args.get_web_error_status()?;
+                        NavigationCompleted {
+                            url,
+                            is_success: false,
+                            http_status: http_status(status),
+                            error: Some(web_error_kind(status)),
+                        }
+                    };
+                    f.0(&event);
+                    Ok(())
+                },
+            ))?;
+        let control = self.control.clone();
+        Ok(Subscription::new(move || {
+            let _ = control.remove_navigation_completed(token);
+        }))
+    }
 
     /*
     pub fn add_accelerator_key_pressed<F>(&self, f: F)
-        -> Result<EventRegistrationToken, winrt::Error>
+        -> Result<Subscription, winrt::Error>
         where F: FnMut(TODO) + 'static
     {
         let mut f = FakeSend(f);
@@ -528,64 +1616,162 @@ impl EdgeWebViewControl {
     pub fn add_contains_full_screen_element_changed<F>(
         &self,
         f: F,
-    ) -> Result<EventRegistrationToken, winrt::Error>
+    ) -> Result<Subscription, winrt::Error>
     where
         F: FnMut(bool) + 'static,
     {
         let mut f = FakeSend(f);
-        self.control
-            .add_contains_full_screen_element_changed(&TypedEventHandler::new(
-                move |sender: *mut IWebViewControl, _args| {
-                    let sender = unsafe { &mut *sender };
-                    f.0(sender.get_contains_full_screen_element()?);
-                    Ok(())
-                },
-            ))
+        let token =
+            self.control
+                .add_contains_full_screen_element_changed(&TypedEventHandler::new(
+                    move |sender: *mut IWebViewControl, _args| {
+                        let sender = unsafe { &mut *sender };
+                        f.0(sender.get_contains_full_screen_element()?);
+                        Ok(())
+                    },
+                ))?;
+        let control = self.control.clone();
+        Ok(Subscription::new(move || {
+            let _ = control.remove_contains_full_screen_element_changed(token);
+        }))
     }
 
-    /*
-    pub fn add_new_window_requested<F>(&self, f: F)
-        -> Result<EventRegistrationToken, winrt::Error>
-        where F: FnMut(TODO) + 'static
+    pub fn add_new_window_requested<F>(&self, f: F) -> Result<Subscription, winrt::Error>
+    where
+        F: FnMut(&NewWindowRequested) + 'static,
     {
         let mut f = FakeSend(f);
-        self.control.add_new_window_requested(&TypedEventHandler::new(
-            move |_sender, args: *mut _| {
-                let args = unsafe { &mut *args };
-                f.0(args);
-                Ok(())
-            }
-        ))
+        let token = self
+            .control
+            .add_new_window_requested(&TypedEventHandler::new(
+                move |sender: *mut IWebViewControl,
+                      args: *mut WebViewControlNewWindowRequestedEventArgs| {
+                    let args = unsafe { add_ref(args) };
+                    let uri = args.get_uri()?.ok_or(winrt::Error::UnspecifiedFailure)?;
+                    let url = uri.get_absolute_uri()?.to_string();
+                    let referrer = uri_string(args.get_referrer()?)?;
+                    let referrer = if referrer.is_empty() {
+                        None
+                    } else {
+                        Some(referrer)
+                    };
+                    let event = NewWindowRequested::new(url.clone(), referrer);
+                    f.0(&event);
+                    let action = match event.take_action() {
+                        Some(action) => action,
+                        None => return Ok(()),
+                    };
+                    args.set_handled(true)?;
+                    match action {
+                        NewWindowAction::Ignore => {}
+                        NewWindowAction::NavigateHere => {
+                            let sender = unsafe { &*sender };
+                            sender.navigate(&uri)?;
+                        }
+                        NewWindowAction::Open(crate::WebViewControl::EdgeHTML(control)) => {
+                            match args
+                                .query_interface::<IWebViewControlNewWindowRequestedEventArgs2>()
+                            {
+                                Some(args) => {
+                                    // The new control may well not have been created yet, so make
+                                    // the page wait for it.
+                                    let deferral = args
+                                        .get_deferral()?
+                                        .ok_or(winrt::Error::UnspecifiedFailure)?;
+                                    let deferral = CompleteOnDrop(deferral);
+                                    // If its creation has failed, dropping the deferral with the
+                                    // operation lets the page carry on without it.
+                                    let _ = control.run_or_queue(move |control| {
+                                        args.set_new_window(&control.control)?;
+                                        drop(deferral);
+                                        Ok(())
+                                    });
+                                }
+                                // Older versions of Windows can’t keep the opener.
+                                None => {
+                                    let _ = control.navigate(&url);
+                                }
+                            }
+                        }
+                        #[allow(unreachable_patterns)]
+                        NewWindowAction::Open(control) => {
+                            let _ = control.navigate(&url);
+                        }
+                    }
+                    Ok(())
+                },
+            ))?;
+        let control = self.control.clone();
+        Ok(Subscription::new(move || {
+            let _ = control.remove_new_window_requested(token);
+        }))
     }
 
-    pub fn add_permission_requested<F>(&self, f: F)
-        -> Result<EventRegistrationToken, winrt::Error>
-        where F: FnMut(TODO) + 'static
+    pub fn add_permission_requested<F>(&self, f: F) -> Result<Subscription, winrt::Error>
+    where
+        F: FnMut(&PermissionRequest) + 'static,
     {
         let mut f = FakeSend(f);
-        self.control.add_permission_requested(&TypedEventHandler::new(
-            move |_sender, args: *mut _| {
-                let args = unsafe { &mut *args };
-                f.0(args);
-                Ok(())
-            }
-        ))
+        let token = self
+            .control
+            .add_permission_requested(&TypedEventHandler::new(
+                move |sender: *mut IWebViewControl,
+                      args: *mut WebViewControlPermissionRequestedEventArgs| {
+                    let args = unsafe { &mut *args };
+                    let request = args
+                        .get_permission_request()?
+                        .ok_or(winrt::Error::UnspecifiedFailure)?;
+                    let url = uri_string(request.get_uri()?)?;
+                    let origin = navigation::origin_of(&url).unwrap_or(url);
+                    let control = FakeSend(unsafe { add_ref(sender) });
+                    let id = request.get_id()?;
+                    let original = request.clone();
+                    let event = PermissionRequest::new(
+                        permission_kind(request.get_permission_type()?),
+                        origin,
+                        move || {
+                            // The handler may answer before it returns, so this must be deferred
+                            // now for the answer to reach the deferred request.
+                            let deferred = original.defer().is_ok();
+                            move |allow| {
+                                let _ = if deferred {
+                                    respond_to_deferred_permission_request(&control.0, id, allow)
+                                } else if allow {
+                                    original.allow()
+                                } else {
+                                    original.deny()
+                                };
+                            }
+                        },
+                    );
+                    f.0(&event);
+                    match event.state() {
+                        PermissionState::Unanswered | PermissionState::Deferred => (),
+                        PermissionState::Allowed => request.allow()?,
+                        PermissionState::Denied => request.deny()?,
+                    }
+                    Ok(())
+                },
+            ))?;
+        let control = self.control.clone();
+        Ok(Subscription::new(move || {
+            let _ = control.remove_permission_requested(token);
+        }))
     }
-    */
 
     /// Define a function to handle script notifications triggered from JavaScript like this:
     ///
     /// ```javascript
     /// window.external.notify(string)
     /// ```
-    pub fn add_script_notify<F>(&self, f: F) -> Result<EventRegistrationToken, winrt::Error>
+    pub fn add_script_notify<F>(&self, f: F) -> Result<Subscription, winrt::Error>
     where
         F: FnMut(String) + 'static,
     {
         // I do not know whether TypedEventHandler is unconditionally handled in the same thread or
         // not; but for our case at least, we do not need its Sendness. Let’s live dangerously!
         let mut f = FakeSend(f);
-        self.control.add_script_notify(&TypedEventHandler::new(
+        let token = self.control.add_script_notify(&TypedEventHandler::new(
             move |_sender, args: *mut WebViewControlScriptNotifyEventArgs| {
                 let args = unsafe { &mut *args };
                 // args also has get_uri(), but I figure we don’t need it… for now, at least.
@@ -593,11 +1779,15 @@ impl EdgeWebViewControl {
                 f.0(value);
                 Ok(())
             },
-        ))
+        ))?;
+        let control = self.control.clone();
+        Ok(Subscription::new(move || {
+            let _ = control.remove_script_notify(token);
+        }))
     }
 
     /*
-    pub fn add_unsafe_content_warning_displaying<F>(&self, f: F) -> Result<EventRegistrationToken, winrt::Error>
+    pub fn add_unsafe_content_warning_displaying<F>(&self, f: F) -> Result<Subscription, winrt::Error>
     where F: FnMut(TODO) + 'static
     {
         let mut f = FakeSend(f);
@@ -610,7 +1800,7 @@ impl EdgeWebViewControl {
         ))
     }
 
-    pub fn add_unsupported_uri_scheme_identified<F>(&self, f: F) -> Result<EventRegistrationToken, winrt::Error>
+    pub fn add_unsupported_uri_scheme_identified<F>(&self, f: F) -> Result<Subscription, winrt::Error>
     where F: FnMut(TODO) + 'static
     {
         let mut f = FakeSend(f);
@@ -623,7 +1813,7 @@ impl EdgeWebViewControl {
         ))
     }
 
-    pub fn add_unviewable_content_identified<F>(&self, f: F) -> Result<EventRegistrationToken, winrt::Error>
+    pub fn add_unviewable_content_identified<F>(&self, f: F) -> Result<Subscription, winrt::Error>
     where F: FnMut(TODO) + 'static
     {
         let mut f = FakeSend(f);
@@ -635,18 +1825,53 @@ impl EdgeWebViewControl {
             }
         ))
     }
+    */
 
-    pub fn add_web_resource_requested<F>(&self, f: F) -> Result<EventRegistrationToken, winrt::Error>
-    where F: FnMut(TODO) + 'static
+    /// Add a handler for requests the page makes, which can block them, change their headers or
+    /// answer them itself.
+    pub fn add_web_resource_requested<F>(&self, f: F) -> Result<Subscription, winrt::Error>
+    where
+        F: FnMut(&WebResourceRequest) + 'static,
     {
         let mut f = FakeSend(f);
-        self.control.add_web_resource_requested(&TypedEventHandler::new(
-            move |_sender, args: *mut _| {
-                let args = unsafe { &mut *args };
-                f.0(args);
-                Ok(())
-            }
-        ))
+        let token = self
+            .control
+            .add_web_resource_requested(&TypedEventHandler::new(
+                move |_sender, args: *mut WebViewControlWebResourceRequestedEventArgs| {
+                    let args = unsafe { &mut *args };
+                    let request = args
+                        .get_request()?
+                        .ok_or(winrt::Error::UnspecifiedFailure)?;
+                    let method = request
+                        .get_method()?
+                        .ok_or(winrt::Error::UnspecifiedFailure)?
+                        .get_method()?
+                        .to_string();
+                    let url = uri_string(request.get_request_uri()?)?;
+                    let headers = request
+                        .get_headers()?
+                        .ok_or(winrt::Error::UnspecifiedFailure)?;
+                    let event = WebResourceRequest::new(url, method, header_pairs(&headers)?);
+                    f.0(&event);
+                    match event.take_action() {
+                        None => {
+                            if let Some(changed) = event.changed_headers() {
+                                replace_headers(&headers, &changed)?;
+                            }
+                        }
+                        Some(ResourceAction::Block) => args.set_response(
+                            &*HttpResponseMessage::create(HttpStatusCode::Forbidden)?,
+                        )?,
+                        Some(ResourceAction::Respond(response)) => {
+                            args.set_response(&*http_response(response)?)?
+                        }
+                    }
+                    Ok(())
+                },
+            ))?;
+        let control = self.control.clone();
+        Ok(Subscription::new(move || {
+            let _ = control.remove_web_resource_requested(token);
+        }))
     }
-    */
 }
@@ -0,0 +1,444 @@
+//! Custom URI schemes for EdgeHTML, by way of its local stream URIs.
+//!
+//! NavigateToLocalStreamUri wants an IUriToStreamResolver, which returns an
+//! IAsyncOperation<IInputStream>. The winrt crate can only implement delegates, not interfaces, so
+//! both are implemented here by hand. The handler runs synchronously, so the operation is always
+//! complete by the time it’s returned, which keeps it simple.
+//!
+//! Neither object claims to be agile, so COM makes all calls on the thread that created them.
+
+#![allow(non_snake_case)]
+
+use std::mem;
+use std::ptr;
+use std::rc::Rc;
+use std::sync::atomic::{self, AtomicUsize};
+
+use winapi::shared::guiddef::{IsEqualGUID, IID, REFIID};
+use winapi::shared::minwindef::ULONG;
+use winapi::shared::ntdef::HRESULT;
+use winapi::shared::winerror::{E_FAIL, E_NOINTERFACE, E_POINTER, S_OK};
+use winapi::um::unknwnbase::{IUnknown, IUnknownVtbl};
+use winapi::winrt::hstring::HSTRING;
+use winapi::winrt::inspectable::{BaseTrust, TrustLevel};
+
+use winrt::windows::foundation::{
+    AsyncOperationCompletedHandler, AsyncStatus, HResult, IAsyncInfo, IAsyncInfoVtbl,
+    IAsyncOperation, IAsyncOperationVtbl, Uri,
+};
+use winrt::windows::storage::streams::{
+    DataWriter, IInputStream, IOutputStream, InMemoryRandomAccessStream,
+};
+use winrt::windows::web::{IUriToStreamResolver, IUriToStreamResolverVtbl};
+use winrt::{
+    ComIid, ComPtr, IInspectable, IInspectableVtbl, RtAsyncOperation, RtDefaultConstructible,
+};
+
+use crate::scheme::{Body, SchemeHandler, SchemeRequest};
+
+// HTTP_E_STATUS_* are all 0x80190000 plus the status code.
+const HTTP_E_STATUS_BASE: u32 = 0x8019_0000;
+
+/// Create a stream resolver that serves requests with the handler.
+pub(crate) fn stream_resolver(handler: Rc<SchemeHandler>) -> ComPtr<IUriToStreamResolver> {
+    let resolver = Box::new(StreamResolver {
+        vtbl: &RESOLVER_VTBL,
+        refcount: AtomicUsize::new(1),
+        handler,
+    });
+    unsafe { ComPtr::wrap(Box::into_raw(resolver) as *mut IUriToStreamResolver) }
+}
+
+#[repr(C)]
+struct StreamResolver {
+    vtbl: *const IUriToStreamResolverVtbl,
+    refcount: AtomicUsize,
+    handler: Rc<SchemeHandler>,
+}
+
+impl StreamResolver {
+    fn resolve(&self, uri: &Uri) -> Result<ComPtr<IInputStream>, HRESULT> {
+        let path = uri.get_path().map_err(|e| e.as_hresult())?.to_string();
+        let request = SchemeRequest {
+            method: "GET".to_owned(),
+            uri: uri
+                .get_absolute_uri()
+                .map_err(|e| e.as_hresult())?
+                .to_string(),
+            path,
+            headers: Vec::new(),
+        };
+        let response = (self.handler)(&request);
+        if !(200..300).contains(&response.status) {
+            return Err((HTTP_E_STATUS_BASE | u32::from(response.status)) as HRESULT);
+        }
+        let body = match response.body {
+            Body::Bytes(bytes) => bytes,
+            body => body.into_bytes().map_err(|_| E_FAIL)?,
+        };
+        input_stream(&body).map_err(|e| e.as_hresult())
+    }
+}
+
+/// Put the bytes in a stream.
+fn input_stream(bytes: &[u8]) -> Result<ComPtr<IInputStream>, winrt::Error> {
+    let writer = DataWriter::new();
+    writer.write_bytes(bytes)?;
+    let buffer = writer
+        .detach_buffer()?
+        .ok_or(winrt::Error::UnspecifiedFailure)?;
+    let stream = InMemoryRandomAccessStream::new();
+    let output_stream = stream
+        .query_interface::<IOutputStream>()
+        .ok_or(winrt::Error::NoSuchInterface)?;
+    // Writing to memory is quick, and blocking_get waits on a condition variable rather than
+    // needing this thread’s message loop, so there’s no harm in waiting.
+    output_stream.write_async(&buffer)?.blocking_get()?;
+    stream
+        .get_input_stream_at(0)?
+        .ok_or(winrt::Error::UnspecifiedFailure)
+}
+
+static RESOLVER_VTBL: IUriToStreamResolverVtbl = IUriToStreamResolverVtbl {
+    parent: IInspectableVtbl {
+        parent: IUnknownVtbl {
+            QueryInterface: Resolver_QueryInterface,
+            AddRef: Resolver_AddRef,
+            Release: Resolver_Release,
+        },
+        GetIids: Inspectable_GetIids,
+        GetRuntimeClassName: Inspectable_GetRuntimeClassName,
+        GetTrustLevel: Inspectable_GetTrustLevel,
+    },
+    UriToStreamAsync: Resolver_UriToStreamAsync,
+};
+
+unsafe extern "system" fn Resolver_QueryInterface(
+    this: *mut IUnknown,
+    iid: REFIID,
+    object: *mut *mut winapi::ctypes::c_void,
+) -> HRESULT {
+    if object.is_null() {
+        return E_POINTER;
+    }
+    if is_iid::<IUnknown>(iid) || is_iid::<IInspectable>(iid) || is_iid::<IUriToStreamResolver>(iid)
+    {
+        Resolver_AddRef(this);
+        *object = this as *mut _;
+        S_OK
+    } else {
+        *object = ptr::null_mut();
+        E_NOINTERFACE
+    }
+}
+
+unsafe extern "system" fn Resolver_AddRef(this: *mut IUnknown) -> ULONG {
+    let this = &*(this as *const StreamResolver);
+    (this.refcount.fetch_add(1, atomic::Ordering::Relaxed) + 1) as ULONG
+}
+
+unsafe extern "system" fn Resolver_Release(this: *mut IUnknown) -> ULONG {
+    let remaining = (*(this as *const StreamResolver))
+        .refcount
+        .fetch_sub(1, atomic::Ordering::Release)
+        - 1;
+    if remaining == 0 {
+        atomic::fence(atomic::Ordering::Acquire);
+        drop(Box::from_raw(this as *mut StreamResolver));
+    }
+    remaining as ULONG
+}
+
+unsafe extern "system" fn Resolver_UriToStreamAsync(
+    this: *mut IUriToStreamResolver,
+    uri: *mut Uri,
+    out: *mut *mut IAsyncOperation<IInputStream>,
+) -> HRESULT {
+    if uri.is_null() || out.is_null() {
+        return E_POINTER;
+    }
+    let this = &*(this as *const StreamResolver);
+    *out = CompletedOperation::create(this.resolve(&*uri));
+    S_OK
+}
+
+/// An IAsyncOperation<IInputStream> that has already completed.
+#[repr(C)]
+struct CompletedOperation {
+    // IAsyncOperation<IInputStream> is the primary interface; a pointer to `info_vtbl` is the
+    // IAsyncInfo interface.
+    vtbl: *const IAsyncOperationVtbl<IInputStream>,
+    info_vtbl: *const IAsyncInfoVtbl,
+    refcount: AtomicUsize,
+    result: Result<ComPtr<IInputStream>, HRESULT>,
+    completed: Option<ComPtr<AsyncOperationCompletedHandler<IInputStream>>>,
+}
+
+impl CompletedOperation {
+    /// Allocate one, returning its one reference for the caller to own.
+    fn create(result: Result<ComPtr<IInputStream>, HRESULT>) -> *mut IAsyncOperation<IInputStream> {
+        let operation = Box::new(CompletedOperation {
+            vtbl: &OPERATION_VTBL,
+            info_vtbl: &INFO_VTBL,
+            refcount: AtomicUsize::new(1),
+            result,
+            completed: None,
+        });
+        Box::into_raw(operation) as *mut IAsyncOperation<IInputStream>
+    }
+
+    fn status(&self) -> AsyncStatus {
+        match self.result {
+            Ok(_) => AsyncStatus::Completed,
+            Err(_) => AsyncStatus::Error,
+        }
+    }
+
+    unsafe fn from_operation<'a, T>(this: *mut T) -> &'a mut CompletedOperation {
+        &mut *(this as *mut CompletedOperation)
+    }
+
+    unsafe fn from_info<'a, T>(this: *mut T) -> &'a mut CompletedOperation {
+        &mut *((this as *mut u8).sub(mem::size_of::<usize>()) as *mut CompletedOperation)
+    }
+
+    unsafe fn query_interface(
+        &mut self,
+        iid: REFIID,
+        object: *mut *mut winapi::ctypes::c_void,
+    ) -> HRESULT {
+        if object.is_null() {
+            return E_POINTER;
+        }
+        *object = if is_iid::<IUnknown>(iid)
+            || is_iid::<IInspectable>(iid)
+            || is_iid::<IAsyncOperation<IInputStream>>(iid)
+        {
+            &mut self.vtbl as *mut _ as *mut _
+        } else if is_iid::<IAsyncInfo>(iid) {
+            &mut self.info_vtbl as *mut _ as *mut _
+        } else {
+            *object = ptr::null_mut();
+            return E_NOINTERFACE;
+        };
+        self.add_ref();
+        S_OK
+    }
+
+    fn add_ref(&self) -> ULONG {
+        (self.refcount.fetch_add(1, atomic::Ordering::Relaxed) + 1) as ULONG
+    }
+
+    unsafe fn release(&mut self) -> ULONG {
+        let remaining = self.refcount.fetch_sub(1, atomic::Ordering::Release) - 1;
+        if remaining == 0 {
+            atomic::fence(atomic::Ordering::Acquire);
+            drop(Box::from_raw(self as *mut CompletedOperation));
+        }
+        remaining as ULONG
+    }
+}
+
+static OPERATION_VTBL: IAsyncOperationVtbl<IInputStream> = IAsyncOperationVtbl {
+    parent: IInspectableVtbl {
+        parent: IUnknownVtbl {
+            QueryInterface: Operation_QueryInterface,
+            AddRef: Operation_AddRef,
+            Release: Operation_Release,
+        },
+        GetIids: Inspectable_GetIids,
+        GetRuntimeClassName: Inspectable_GetRuntimeClassName,
+        GetTrustLevel: Inspectable_GetTrustLevel,
+    },
+    put_Completed: Operation_put_Completed,
+    get_Completed: Operation_get_Completed,
+    GetResults: Operation_GetResults,
+};
+
+unsafe extern "system" fn Operation_QueryInterface(
+    this: *mut IUnknown,
+    iid: REFIID,
+    object: *mut *mut winapi::ctypes::c_void,
+) -> HRESULT {
+    CompletedOperation::from_operation(this).query_interface(iid, object)
+}
+
+unsafe extern "system" fn Operation_AddRef(this: *mut IUnknown) -> ULONG {
+    CompletedOperation::from_operation(this).add_ref()
+}
+
+unsafe extern "system" fn Operation_Release(this: *mut IUnknown) -> ULONG {
+    CompletedOperation::from_operation(this).release()
+}
+
+unsafe extern "system" fn Operation_put_Completed(
+    this: *mut IAsyncOperation<IInputStream>,
+    handler: *mut AsyncOperationCompletedHandler<IInputStream>,
+) -> HRESULT {
+    if handler.is_null() {
+        return E_POINTER;
+    }
+    let operation = CompletedOperation::from_operation(this);
+    // We need our own reference to the handler; cloning one we don’t own gets us that.
+    let borrowed = mem::ManuallyDrop::new(ComPtr::wrap(handler));
+    let handler = (*borrowed).clone();
+    operation.completed = Some(handler.clone());
+    // Already complete, so the handler is called straight away, as the contract requires.
+    match handler.invoke(&*this, operation.status()) {
+        Ok(()) => S_OK,
+        Err(error) => error.as_hresult(),
+    }
+}
+
+unsafe extern "system" fn Operation_get_Completed(
+    this: *mut IAsyncOperation<IInputStream>,
+    out: *mut *mut AsyncOperationCompletedHandler<IInputStream>,
+) -> HRESULT {
+    if out.is_null() {
+        return E_POINTER;
+    }
+    let operation = CompletedOperation::from_operation(this);
+    *out = match operation.completed {
+        Some(ref handler) => {
+            let handler = handler.clone();
+            let raw = &*handler as *const _ as *mut _;
+            mem::forget(handler);
+            raw
+        }
+        None => ptr::null_mut(),
+    };
+    S_OK
+}
+
+unsafe extern "system" fn Operation_GetResults(
+    this: *mut IAsyncOperation<IInputStream>,
+    out: *mut *mut IInputStream,
+) -> HRESULT {
+    if out.is_null() {
+        return E_POINTER;
+    }
+    match CompletedOperation::from_operation(this).result {
+        Ok(ref stream) => {
+            let stream = stream.clone();
+            *out = &*stream as *const _ as *mut _;
+            mem::forget(stream);
+            S_OK
+        }
+        Err(hresult) => {
+            *out = ptr::null_mut();
+            hresult
+        }
+    }
+}
+
+static INFO_VTBL: IAsyncInfoVtbl = IAsyncInfoVtbl {
+    parent: IInspectableVtbl {
+        parent: IUnknownVtbl {
+            QueryInterface: Info_QueryInterface,
+            AddRef: Info_AddRef,
+            Release: Info_Release,
+        },
+        GetIids: Inspectable_GetIids,
+        GetRuntimeClassName: Inspectable_GetRuntimeClassName,
+        GetTrustLevel: Inspectable_GetTrustLevel,
+    },
+    get_Id: Info_get_Id,
+    get_Status: Info_get_Status,
+    get_ErrorCode: Info_get_ErrorCode,
+    Cancel: Info_Cancel,
+    Close: Info_Close,
+};
+
+unsafe extern "system" fn Info_QueryInterface(
+    this: *mut IUnknown,
+    iid: REFIID,
+    object: *mut *mut winapi::ctypes::c_void,
+) -> HRESULT {
+    CompletedOperation::from_info(this).query_interface(iid, object)
+}
+
+unsafe extern "system" fn Info_AddRef(this: *mut IUnknown) -> ULONG {
+    CompletedOperation::from_info(this).add_ref()
+}
+
+unsafe extern "system" fn Info_Release(this: *mut IUnknown) -> ULONG {
+    CompletedOperation::from_info(this).release()
+}
+
+unsafe extern "system" fn Info_get_Id(_this: *mut IAsyncInfo, out: *mut u32) -> HRESULT {
+    if out.is_null() {
+        return E_POINTER;
+    }
+    *out = 1;
+    S_OK
+}
+
+unsafe extern "system" fn Info_get_Status(this: *mut IAsyncInfo, out: *mut AsyncStatus) -> HRESULT {
+    if out.is_null() {
+        return E_POINTER;
+    }
+    *out = CompletedOperation::from_info(this).status();
+    S_OK
+}
+
+unsafe extern "system" fn Info_get_ErrorCode(this: *mut IAsyncInfo, out: *mut HResult) -> HRESULT {
+    if out.is_null() {
+        return E_POINTER;
+    }
+    *out = HResult {
+        Value: match CompletedOperation::from_info(this).result {
+            Ok(_) => S_OK,
+            Err(hresult) => hresult,
+        },
+    };
+    S_OK
+}
+
+unsafe extern "system" fn Info_Cancel(_this: *mut IAsyncInfo) -> HRESULT {
+    S_OK
+}
+
+unsafe extern "system" fn Info_Close(_this: *mut IAsyncInfo) -> HRESULT {
+    S_OK
+}
+
+// --- IInspectable, the same for both objects ---
+
+unsafe extern "system" fn Inspectable_GetIids(
+    _this: *mut IInspectable,
+    count: *mut ULONG,
+    iids: *mut *mut IID,
+) -> HRESULT {
+    if count.is_null() || iids.is_null() {
+        return E_POINTER;
+    }
+    *count = 0;
+    *iids = ptr::null_mut();
+    S_OK
+}
+
+unsafe extern "system" fn Inspectable_GetRuntimeClassName(
+    _this: *mut IInspectable,
+    name: *mut HSTRING,
+) -> HRESULT {
+    if name.is_null() {
+        return E_POINTER;
+    }
+    *name = ptr::null_mut();
+    S_OK
+}
+
+unsafe extern "system" fn Inspectable_GetTrustLevel(
+    _this: *mut IInspectable,
+    trust_level: *mut TrustLevel,
+) -> HRESULT {
+    if trust_level.is_null() {
+        return E_POINTER;
+    }
+    *trust_level = BaseTrust;
+    S_OK
+}
+
+unsafe fn is_iid<T: ComIid>(iid: REFIID) -> bool {
+    IsEqualGUID(&*iid, &*(T::iid() as *const winrt::Guid as *const IID))
+}
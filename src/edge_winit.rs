@@ -1,9 +1,17 @@
+use std::cell::Cell;
+use std::rc::Rc;
+
 use crate::edge::{self, Control, Process};
 use crate::error::Error;
+use crate::script::Eval;
+use crate::settings::ControlSettings;
+use crate::subscription::Subscription;
+use crate::WebView;
 
 use winapi::shared::windef::HWND;
 
 use winit::dpi::{LogicalPosition, LogicalSize};
+use winit::event::WindowEvent;
 use winit::platform::windows::WindowExtWindows;
 use winit::window::Window;
 
@@ -13,17 +21,14 @@ pub enum HwndType {
     NewHwndInWindow,
 }
 
-pub fn new_control<F>(
+pub fn new_control(
     process: &Process,
     window: &Window,
     hwnd_type: HwndType,
     position: Option<LogicalPosition>,
     size: Option<LogicalSize>,
-    callback: Option<F>,
-) -> Result<Control, Error>
-where
-    F: FnOnce(Control) + 'static,
-{
+    settings: &ControlSettings,
+) -> Result<Control, Error> {
     let window_hwnd = window.hwnd() as *mut _;
     let hwnd_type = match hwnd_type {
         HwndType::FillWindow => edge::HwndType::FillWindow(window_hwnd),
@@ -46,6 +51,141 @@ where
         // the control is created.
         position,
         (size.0 as i32, size.1 as i32),
-        callback,
+        settings,
     )
 }
+
+/// Whether the page has a `beforeunload` handler that objects to being unloaded. A synthetic
+/// `BeforeUnloadEvent` goes through the same handlers as the real one, `onbeforeunload` included.
+const BEFORE_UNLOAD_CHECK: &str = "(function () {
+    var event = document.createEvent('BeforeUnloadEvent');
+    event.initEvent('beforeunload', false, true);
+    window.dispatchEvent(event);
+    return event.defaultPrevented || !!event.returnValue;
+})()";
+
+/// What to do about a window’s close request, once the page has had its say.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CloseOutcome {
+    /// The page doesn’t mind, so close the window.
+    Close,
+    /// The page has unsaved state or the like, and asked to stay open. The engine can’t show its
+    /// own prompt for this, so it is up to the application to ask the user, or not.
+    PageWantsToStay,
+}
+
+/// A control filling a winit window, kept in step with the window by `handle_event`.
+///
+/// ```rust,ignore
+/// let mut web_view = WinitWebView::new(&process, &window, &ControlSettings::new())?;
+/// event_loop.run_return(|event, _, control_flow| {
+///     *control_flow = ControlFlow::Wait;
+///     if let Event::WindowEvent { event, .. } = event {
+///         web_view.handle_event(&event).unwrap();
+///     }
+///     if web_view.poll_close() == Some(CloseOutcome::Close) {
+///         *control_flow = ControlFlow::Exit;
+///     }
+/// });
+/// ```
+pub struct WinitWebView {
+    control: Control,
+    size: LogicalSize,
+    hidpi_factor: f64,
+    window_focused: Rc<Cell<bool>>,
+    // Whether the control had focus when the window last had it, and so should get it back.
+    control_focused: Rc<Cell<bool>>,
+    _focus_changed: Subscription,
+    close_check: Option<Eval<bool>>,
+}
+
+impl WinitWebView {
+    /// Create a control filling the window, and focus it.
+    pub fn new(
+        process: &Process,
+        window: &Window,
+        settings: &ControlSettings,
+    ) -> Result<WinitWebView, Error> {
+        let control = new_control(process, window, HwndType::FillWindow, None, None, settings)?;
+        let window_focused = Rc::new(Cell::new(true));
+        let control_focused = Rc::new(Cell::new(true));
+        let focus_changed = {
+            let window_focused = window_focused.clone();
+            let control_focused = control_focused.clone();
+            control.add_focus_changed(move |focused| {
+                // The control loses focus along with the window, but that’s no reason not to give
+                // it back when the window gets focus again.
+                if focused || window_focused.get() {
+                    control_focused.set(focused);
+                }
+            })?
+        };
+        control.focus()?;
+        Ok(WinitWebView {
+            control,
+            size: window.inner_size(),
+            hidpi_factor: window.hidpi_factor(),
+            window_focused,
+            control_focused,
+            _focus_changed: focus_changed,
+            close_check: None,
+        })
+    }
+
+    pub fn control(&self) -> &Control {
+        &self.control
+    }
+
+    /// Update the control for an event of its window:
+    ///
+    /// - `Focused`: refocus the control when the window regains focus, if it had focus before;
+    /// - `Resized` and `HiDpiFactorChanged`: resize the control to fill the window;
+    /// - `CloseRequested`: ask the page whether it minds being closed, which `poll_close` gives the
+    ///   answer to.
+    ///
+    /// Events from other windows should not be passed in.
+    pub fn handle_event(&mut self, event: &WindowEvent) -> Result<(), Error> {
+        match *event {
+            WindowEvent::Focused(false) => self.window_focused.set(false),
+            WindowEvent::Focused(true) => {
+                self.window_focused.set(true);
+                if self.control_focused.get() {
+                    self.control.focus()?;
+                }
+            }
+            WindowEvent::Resized(size) => {
+                self.size = size;
+                self.fill_window()?;
+            }
+            WindowEvent::HiDpiFactorChanged(hidpi_factor) => {
+                self.hidpi_factor = hidpi_factor;
+                self.fill_window()?;
+            }
+            WindowEvent::CloseRequested if self.close_check.is_none() => {
+                self.close_check = Some(self.control.eval(BEFORE_UNLOAD_CHECK));
+            }
+            _ => (),
+        }
+        Ok(())
+    }
+
+    /// The outcome of the last close request, once the page has answered.
+    ///
+    /// The page answers asynchronously, so call this whenever the event loop wakes up, not only
+    /// after `CloseRequested`. If the page can’t answer, e.g. because the control failed, the
+    /// outcome is `Close`.
+    pub fn poll_close(&mut self) -> Option<CloseOutcome> {
+        let result = self.close_check.as_mut()?.try_take()?;
+        self.close_check = None;
+        Some(match result {
+            Ok(true) => CloseOutcome::PageWantsToStay,
+            Ok(false) | Err(_) => CloseOutcome::Close,
+        })
+    }
+
+    fn fill_window(&self) -> Result<(), Error> {
+        let size: (u32, u32) = self.size.to_physical(self.hidpi_factor).into();
+        self.control
+            .resize(None, Some((size.0 as i32, size.1 as i32)))
+    }
+}
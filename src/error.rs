@@ -4,27 +4,124 @@ use std::io;
 #[cfg(all(windows, feature = "edgehtml"))]
 use winrt;
 
+#[cfg(feature = "gtk-webkit2")]
+use glib;
+
+use crate::capabilities::Capability;
+use crate::navigation::WebErrorKind;
+use crate::Backend;
+
 /// webviewcontrol’s uniform error type.
 ///
 /// The particular variants that are available vary by platform. Here are the variants you can
 /// expect:
 ///
-/// - EdgeHTML: the poorly named `Io` for OS errors (the HWND side of things), or `Rt` for WinRT
-///   errors (the WebViewControl side of things). As the WinRT errors don’t implement
-///   `std::error::Error`, the `source()` method will return `None` for these.
+/// - All backends: `NotReady` for operations the control can’t do yet; `CreationFailed` for
+///   operations on a control that could not be created; `BackendUnavailable` if the engine isn’t
+///   there to create controls with, and `NoBackendAvailable` if none is; `InvalidParent` for
+///   windows a control can’t be put in; `InvalidUrl` for URLs that can’t be navigated to;
+///   `InvalidZoom` for zoom factors that aren’t positive and finite; `Script` for exceptions
+///   thrown by scripts run with `WebView::eval` or `WebView::call`; `Json` for values that could
+///   not be serialised or deserialised on their way to or from them; `Navigation` for failed
+///   navigations, from `NavigationCompleted::result`; and `Unsupported` for things the engine
+///   can’t do at all.
+/// - EdgeHTML: the poorly named `Io` for OS errors (the HWND side of things), or `Rt` for any
+///   other WinRT errors (the WebViewControl side of things).
+/// - WebKit2GTK: `Glib` for any other errors reported by GLib, WebKit or GTK.
+///
+/// Native errors that mean one of the uniform variants are converted to it; for example, WinRT’s
+/// `E_ILLEGAL_METHOD_CALL` becomes `NotReady`.
 #[derive(Debug)]
 pub enum Error {
     Io(io::Error),
+    /// The control isn’t in a state to do that yet, e.g. because it is still being created.
+    NotReady,
+    /// The control could not be created, so the operation can never happen.
+    CreationFailed,
+    /// The engine can’t be used, for the reason given: it isn’t installed, or is too old, or
+    /// the toolkit it needs hasn’t been initialised.
+    BackendUnavailable(String),
+    /// `WebViewControl::new` couldn’t create a control with any backend. Each backend tried is
+    /// here with the reason it failed; if there are none, no backends were enabled at compile
+    /// time.
+    NoBackendAvailable(Vec<(Backend, Error)>),
+    /// The window a control was to be put in isn’t one the backend can use, for the reason given.
+    InvalidParent(String),
+    /// The URL is not one that can be navigated to, lacking a scheme or containing control
+    /// characters.
+    InvalidUrl(String),
+    /// The zoom factor given to `WebView::set_zoom` is not a positive, finite number.
+    InvalidZoom(f64),
+    /// A script threw an exception. The message is the exception converted to a string, e.g.
+    /// “ReferenceError: foo is not defined”; the stack is there if the engine provided one.
+    Script {
+        message: String,
+        stack: Option<String>,
+    },
+    Json(serde_json::Error),
+    /// A navigation failed. `http_status` is there for `WebErrorKind::Http`, at least.
+    Navigation {
+        url: String,
+        kind: WebErrorKind,
+        http_status: Option<u16>,
+    },
+    /// The engine can’t do that; see `WebView::capabilities`.
+    Unsupported(Capability),
     #[cfg(all(windows, feature = "edgehtml"))]
-    Rt(winrt::Error),
+    Rt(RtError),
+    #[cfg(feature = "gtk-webkit2")]
+    Glib(glib::Error),
 }
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
             Error::Io(ref err) => write!(f, "I/O error: {}", err),
+            Error::NotReady => f.write_str("the web view control is not ready yet"),
+            Error::CreationFailed => f.write_str("the web view control could not be created"),
+            Error::BackendUnavailable(ref reason) => {
+                write!(f, "the web view engine is unavailable: {}", reason)
+            }
+            Error::NoBackendAvailable(ref failures) => {
+                f.write_str("no web view engine is available")?;
+                if failures.is_empty() {
+                    return f.write_str(" (none were enabled at compile time)");
+                }
+                for (i, (backend, error)) in failures.iter().enumerate() {
+                    f.write_str(if i == 0 { ": " } else { "; " })?;
+                    match *error {
+                        Error::BackendUnavailable(ref reason) => {
+                            write!(f, "{}: {}", backend, reason)?
+                        }
+                        ref error => write!(f, "{}: {}", backend, error)?,
+                    }
+                }
+                Ok(())
+            }
+            Error::InvalidParent(ref reason) => write!(f, "invalid parent window: {}", reason),
+            Error::InvalidUrl(ref url) => write!(f, "invalid URL: {:?}", url),
+            Error::InvalidZoom(zoom) => write!(f, "invalid zoom factor: {}", zoom),
+            Error::Script { ref message, .. } => write!(f, "script exception: {}", message),
+            Error::Json(ref err) => write!(f, "JSON error: {}", err),
+            Error::Navigation {
+                ref url,
+                ref kind,
+                http_status,
+            } => {
+                write!(f, "navigation to {} failed: ", url)?;
+                match (kind, http_status) {
+                    (WebErrorKind::Other(description), _) => f.write_str(description),
+                    (_, Some(status)) => write!(f, "HTTP status {}", status),
+                    (kind, None) => write!(f, "{:?}", kind),
+                }
+            }
+            Error::Unsupported(capability) => {
+                write!(f, "the web view engine doesn’t support {:?}", capability)
+            }
             #[cfg(all(windows, feature = "edgehtml"))]
-            Error::Rt(ref err) => write!(f, "WinRT error: {:?}", err),
+            Error::Rt(ref err) => write!(f, "WinRT error: {}", err),
+            #[cfg(feature = "gtk-webkit2")]
+            Error::Glib(ref err) => write!(f, "GLib error: {}", err),
         }
     }
 }
@@ -33,8 +130,21 @@ impl std::error::Error for Error {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         match *self {
             Error::Io(ref err) => Some(err),
+            Error::NotReady => None,
+            Error::CreationFailed => None,
+            Error::BackendUnavailable(_) => None,
+            Error::NoBackendAvailable(_) => None,
+            Error::InvalidParent(_) => None,
+            Error::InvalidUrl(_) => None,
+            Error::InvalidZoom(_) => None,
+            Error::Script { .. } => None,
+            Error::Json(ref err) => Some(err),
+            Error::Navigation { .. } => None,
+            Error::Unsupported(_) => None,
             #[cfg(all(windows, feature = "edgehtml"))]
-            Error::Rt(_) => None, // Doesn’t implement std::error::Error
+            Error::Rt(ref err) => Some(err),
+            #[cfg(feature = "gtk-webkit2")]
+            Error::Glib(ref err) => Some(err),
         }
     }
 }
@@ -45,9 +155,135 @@ impl From<io::Error> for Error {
     }
 }
 
+impl From<serde_json::Error> for Error {
+    fn from(error: serde_json::Error) -> Error {
+        Error::Json(error)
+    }
+}
+
+/// A WinRT error, wrapped so that it can implement `std::error::Error`.
+#[cfg(all(windows, feature = "edgehtml"))]
+#[derive(Debug, PartialEq, Eq)]
+pub struct RtError(pub winrt::Error);
+
+#[cfg(all(windows, feature = "edgehtml"))]
+impl RtError {
+    /// The HRESULT the error came from.
+    pub fn hresult(&self) -> winrt::HRESULT {
+        self.0.as_hresult()
+    }
+}
+
+#[cfg(all(windows, feature = "edgehtml"))]
+impl fmt::Display for RtError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // The Debug output is the name of the HRESULT, or its value for the unnamed ones.
+        write!(f, "{:?}", self.0)
+    }
+}
+
+#[cfg(all(windows, feature = "edgehtml"))]
+impl std::error::Error for RtError {}
+
+/// REGDB_E_CLASSNOTREG, which is what activating a WinRT class gets on Windows versions that
+/// don’t have it.
+#[cfg(all(windows, feature = "edgehtml"))]
+const CLASS_NOT_REGISTERED: winrt::HRESULT = 0x8004_0154_u32 as winrt::HRESULT;
+
 #[cfg(all(windows, feature = "edgehtml"))]
 impl From<winrt::Error> for Error {
     fn from(error: winrt::Error) -> Error {
-        Error::Rt(error)
+        match error {
+            winrt::Error::IllegalMethodCall => Error::NotReady,
+            winrt::Error::Other(CLASS_NOT_REGISTERED) => Error::BackendUnavailable(
+                "the Windows.Web.UI.Interop.WebViewControl class is not registered".to_owned(),
+            ),
+            error => Error::Rt(RtError(error)),
+        }
+    }
+}
+
+#[cfg(feature = "gtk-webkit2")]
+impl From<glib::Error> for Error {
+    fn from(error: glib::Error) -> Error {
+        Error::Glib(error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error as _;
+
+    use super::*;
+
+    #[test]
+    fn display() {
+        assert_eq!(
+            Error::BackendUnavailable("it isn’t installed".to_owned()).to_string(),
+            "the web view engine is unavailable: it isn’t installed"
+        );
+        assert_eq!(
+            Error::NoBackendAvailable(Vec::new()).to_string(),
+            "no web view engine is available (none were enabled at compile time)"
+        );
+        assert_eq!(
+            Error::InvalidUrl("no scheme".to_owned()).to_string(),
+            "invalid URL: \"no scheme\""
+        );
+        assert_eq!(
+            Error::InvalidZoom(-1.0).to_string(),
+            "invalid zoom factor: -1"
+        );
+        let navigation = |kind, http_status| Error::Navigation {
+            url: "https://example.com/".to_owned(),
+            kind,
+            http_status,
+        };
+        assert_eq!(
+            navigation(WebErrorKind::Http, Some(404)).to_string(),
+            "navigation to https://example.com/ failed: HTTP status 404"
+        );
+        assert_eq!(
+            navigation(WebErrorKind::Timeout, None).to_string(),
+            "navigation to https://example.com/ failed: Timeout"
+        );
+        assert_eq!(
+            navigation(WebErrorKind::Other("blocked".to_owned()), None).to_string(),
+            "navigation to https://example.com/ failed: blocked"
+        );
+        assert_eq!(
+            Error::Unsupported(Capability::Printing).to_string(),
+            "the web view engine doesn’t support Printing"
+        );
+    }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn display_no_backend_available() {
+        let error = Error::NoBackendAvailable(vec![
+            (
+                Backend::Mock,
+                Error::BackendUnavailable("not installed".to_owned()),
+            ),
+            (Backend::Mock, Error::CreationFailed),
+        ]);
+        assert_eq!(
+            error.to_string(),
+            "no web view engine is available: mock: not installed; \
+             mock: the web view control could not be created"
+        );
+    }
+
+    #[test]
+    fn from() {
+        let error = Error::from(io::Error::new(io::ErrorKind::NotFound, "gone"));
+        assert!(matches!(error, Error::Io(ref err) if err.kind() == io::ErrorKind::NotFound));
+        assert_eq!(error.to_string(), "I/O error: gone");
+        assert!(error.source().is_some());
+
+        let error = Error::from(serde_json::from_str::<i32>("nope").unwrap_err());
+        assert!(matches!(error, Error::Json(_)));
+        assert!(error.to_string().starts_with("JSON error: "));
+        assert!(error.source().is_some());
     }
 }
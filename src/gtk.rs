@@ -1,13 +1,1113 @@
-use gtk::Widget;
-use glib_sys::GAsyncQueue;
-
-struct WebViewControl {
-    window: Widget,
-    scroller: Widget,
-    webview: Widget,
-    inspector_window: Widget,
-    queue: *mut GAsyncQueue,
-    ready: int,
-    js_busy: int,
-    should_exit: int,
+//! A control backed by a WebKit2GTK WebView.
+//!
+//! This is the backend intended for Linux and other free desktops. It requires GTK 3 and
+//! WebKit2GTK 4.1 at runtime, and GTK must have been initialised (`gtk::init()`) on the thread
+//! that you create controls from; all use of a control must then happen on that same thread.
+//!
+//! Unlike EdgeHTML, WebKitGTK creates its web views synchronously, so there’s never any need to
+//! queue operations until the control is ready, and `Control::ready()` resolves immediately. It’s
+//! still there so that code written against one backend works with the others.
+
+use std::borrow::Cow;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+
+use gtk::gdk;
+use gtk::gio;
+use gtk::glib;
+use gtk::glib::translate::{FromGlibPtrFull, ToGlibPtr};
+use gtk::prelude::*;
+use javascriptcore::ValueExt;
+use raw_window_handle::{HasRawWindowHandle, RawWindowHandle};
+use serde::de::DeserializeOwned;
+use webkit2gtk::{
+    AutoplayPolicy, GeolocationPermissionRequest, LoadEvent, MediaKeySystemPermissionRequest,
+    NavigationPolicyDecision, NavigationPolicyDecisionExt, NetworkError,
+    NotificationPermissionRequest, PermissionRequestExt, PointerLockPermissionRequest,
+    PolicyDecisionExt, PolicyDecisionType, PolicyError, PrintOperation, PrintOperationExt,
+    SettingsExt, SnapshotOptions, SnapshotRegion, URIRequestExt, URIResponseExt, URISchemeRequest,
+    URISchemeRequestExt, URISchemeResponse, URISchemeResponseExt, UserContentInjectedFrames,
+    UserContentManager, UserContentManagerExt, UserMediaPermissionRequest,
+    UserMediaPermissionRequestExt, UserScript, UserScriptInjectionTime, WebContext, WebContextExt,
+    WebResourceExt, WebView as WebKitWebView, WebViewBuilder, WebViewExt,
+    WebsiteDataAccessPermissionRequest, WebsitePolicies,
+};
+
+use crate::capabilities::{Capabilities, Capability};
+use crate::capture::{self, Capture, Image};
+use crate::error::Error;
+use crate::navigation::{
+    self, ContentLoading, DomContentLoaded, NavigationCompleted, NavigationStarting,
+    NewWindowAction, NewWindowRequested, WebErrorKind,
+};
+use crate::permission::{PermissionKind, PermissionRequest, PermissionState};
+use crate::resource::WebResourceRequest;
+use crate::scheme::{self, Body, SchemeHandler, SchemeRequest, SchemeResponse};
+use crate::script::{self, Eval};
+use crate::settings::{Autoplay, ControlSettings, Setting};
+use crate::subscription::Subscription;
+use crate::zoom;
+use crate::{Color, WebView, WebViewControl};
+
+/// WebKit has no `window.external.notify`, so we provide one that passes the value on to a script
+/// message handler of the same name.
+const SCRIPT_NOTIFY_SHIM: &str = "\
+    window.external.notify = function (value) {
+        window.webkit.messageHandlers.external.postMessage(String(value));
+    };";
+
+/// WebKit has no DOMContentLoaded signal, so the page tells us with a script message.
+const DOM_CONTENT_LOADED_SHIM: &str = "\
+    document.addEventListener('DOMContentLoaded', function () {
+        window.webkit.messageHandlers.domContentLoaded.postMessage(location.href);
+    });";
+
+/// What GTK container to put the web view in, and how to handle positioning and resizing.
+pub enum ContainerType {
+    /// Add the web view to the container (typically a `gtk::Window`), letting the container
+    /// allocate its size. Resizing then only affects the web view’s size request.
+    FillContainer(gtk::Container),
+    /// Put the web view in the fixed container at the requested position; resizing moves and
+    /// resizes it within the container.
+    InFixed(gtk::Fixed),
+}
+
+/// The scheme handlers of every control in a process, by lowercase scheme.
+///
+/// WebKit only lets a scheme be registered once per context, and then for all of its web views, so
+/// the context gets one handler for each scheme that finds the right control’s handler.
+type SchemeRegistry =
+    Rc<RefCell<HashMap<String, Vec<(glib::WeakRef<WebKitWebView>, Rc<SchemeHandler>)>>>>;
+
+#[derive(Clone)]
+pub struct Process {
+    context: WebContext,
+    schemes: SchemeRegistry,
+}
+
+impl Process {
+    pub fn new() -> Process {
+        Process {
+            context: WebContext::new(),
+            schemes: Rc::new(RefCell::new(HashMap::new())),
+        }
+    }
+
+    /// Create a control inside the given container.
+    ///
+    /// The provided position and size are specified in physical pixels.
+    ///
+    /// Every setting is supported except `Setting::IndexedDb`, as WebKitGTK always enables
+    /// IndexedDB.
+    pub fn create_control(
+        &self,
+        container_type: ContainerType,
+        position: (i32, i32),
+        size: (i32, i32),
+        settings: &ControlSettings,
+    ) -> Result<Control, Error> {
+        check_available()?;
+        let web_view = new_web_view(
+            WebKitWebView::builder().web_context(&self.context),
+            settings,
+        );
+        Ok(self.add_control(web_view, None, container_type, position, size, settings))
+    }
+
+    /// Create a control related to another, for `NewWindowRequested::open_in`.
+    ///
+    /// A page opened in a related control keeps its `window.opener` relationship with the page
+    /// that opened it. The two controls share a web process, and so must have been created by the
+    /// same `Process`.
+    pub fn create_related_control(
+        &self,
+        opener: &Control,
+        container_type: ContainerType,
+        position: (i32, i32),
+        size: (i32, i32),
+        settings: &ControlSettings,
+    ) -> Result<Control, Error> {
+        check_available()?;
+        let opener = opener.get_inner();
+        let web_view = new_web_view(WebKitWebView::builder().related_view(&opener), settings);
+        Ok(self.add_control(
+            web_view,
+            Some(opener.downgrade()),
+            container_type,
+            position,
+            size,
+            settings,
+        ))
+    }
+
+    /// Create a control in any window that can provide its raw handle, which must be an X11
+    /// window on the display GTK is using (GDK can’t embed in Wayland surfaces).
+    ///
+    /// The control gets a GTK window of its own, put inside the parent at the requested position;
+    /// resizing moves and resizes that window.
+    pub fn create_control_in_window<W: HasRawWindowHandle>(
+        &self,
+        parent: &W,
+        position: (i32, i32),
+        size: (i32, i32),
+        settings: &ControlSettings,
+    ) -> Result<Control, Error> {
+        self.create_control_in_raw_window(&parent.raw_window_handle(), position, size, settings)
+    }
+
+    pub(crate) fn create_control_in_raw_window(
+        &self,
+        handle: &RawWindowHandle,
+        position: (i32, i32),
+        size: (i32, i32),
+        settings: &ControlSettings,
+    ) -> Result<Control, Error> {
+        check_available()?;
+        let foreign_parent = foreign_window(handle)?;
+        // A popup, so that the window manager leaves it alone until it is reparented.
+        let host_window = gtk::Window::new(gtk::WindowType::Popup);
+        host_window.set_default_size(size.0, size.1);
+        let control = self.create_control(
+            ContainerType::FillContainer(host_window.clone().upcast()),
+            (0, 0),
+            size,
+            settings,
+        )?;
+        host_window.realize();
+        host_window
+            .window()
+            .expect("a realized window has a GdkWindow")
+            .reparent(&foreign_parent, position.0, position.1);
+        host_window.show_all();
+        control.inner.borrow_mut().host_window = Some(host_window);
+        Ok(control)
+    }
+
+    fn add_control(
+        &self,
+        web_view: WebKitWebView,
+        related_view: Option<glib::WeakRef<WebKitWebView>>,
+        container_type: ContainerType,
+        position: (i32, i32),
+        size: (i32, i32),
+        settings: &ControlSettings,
+    ) -> Control {
+        if settings.context_menu_enabled == Some(false) {
+            // Returning true claims the event, and so the menu is never shown.
+            web_view.connect_context_menu(|_, _, _, _| true);
+        }
+        web_view.set_size_request(size.0, size.1);
+        match container_type {
+            ContainerType::FillContainer(ref container) => container.add(&web_view),
+            ContainerType::InFixed(ref fixed) => fixed.put(&web_view, position.0, position.1),
+        }
+        web_view.show();
+
+        Control {
+            inner: Rc::new(RefCell::new(ControlInner {
+                container_type,
+                web_view,
+                related_view,
+                schemes: self.schemes.clone(),
+                unsupported_settings: settings.unsupported(SUPPORTED_SETTINGS),
+                host_window: None,
+            })),
+        }
+    }
+}
+
+/// The settings in `ControlSettings` that WebKitGTK can honour.
+const SUPPORTED_SETTINGS: &[Setting] = &[
+    Setting::JavaScript,
+    Setting::ScriptNotify,
+    Setting::ContextMenu,
+    Setting::DevTools,
+    Setting::UserAgent,
+    Setting::Autoplay,
+];
+
+/// What WebKitGTK can do, which is everything but intercepting resource requests.
+const CAPABILITIES: Capabilities = Capabilities {
+    resource_interception: false,
+    ..Capabilities::ALL
+};
+
+/// Build a web view with the settings that have to be applied as it is constructed. (The context
+/// menu is left to `add_control`.)
+fn new_web_view(builder: WebViewBuilder, settings: &ControlSettings) -> WebKitWebView {
+    let webkit_settings = webkit2gtk::Settings::new();
+    if let Some(enabled) = settings.javascript_enabled {
+        webkit_settings.set_enable_javascript(enabled);
+    }
+    if let Some(enabled) = settings.dev_tools_enabled {
+        webkit_settings.set_enable_developer_extras(enabled);
+    }
+    if let Some(ref user_agent) = settings.user_agent {
+        webkit_settings.set_user_agent(Some(user_agent));
+    }
+    let mut builder =
+        builder
+            .settings(&webkit_settings)
+            .user_content_manager(&new_user_content_manager(
+                settings.script_notify_allowed != Some(false),
+            ));
+    if let Some(autoplay) = settings.autoplay {
+        builder = builder.website_policies(
+            &WebsitePolicies::builder()
+                .autoplay(match autoplay {
+                    Autoplay::Allow => AutoplayPolicy::Allow,
+                    Autoplay::AllowWithoutSound => AutoplayPolicy::AllowWithoutSound,
+                    Autoplay::Deny => AutoplayPolicy::Deny,
+                })
+                .build(),
+        );
+    }
+    builder.build()
+}
+
+/// A user content manager with the shims every control needs, leaving out `window.external.notify`
+/// if script notify isn’t allowed.
+fn new_user_content_manager(script_notify_allowed: bool) -> UserContentManager {
+    let user_content_manager = UserContentManager::new();
+    if script_notify_allowed {
+        user_content_manager.add_script(&UserScript::new(
+            SCRIPT_NOTIFY_SHIM,
+            UserContentInjectedFrames::AllFrames,
+            UserScriptInjectionTime::Start,
+            &[],
+            &[],
+        ));
+        user_content_manager.register_script_message_handler("external");
+    }
+    user_content_manager.add_script(&UserScript::new(
+        DOM_CONTENT_LOADED_SHIM,
+        UserContentInjectedFrames::TopFrame,
+        UserScriptInjectionTime::Start,
+        &[],
+        &[],
+    ));
+    user_content_manager.register_script_message_handler("domContentLoaded");
+    user_content_manager
+}
+
+/// Wrap the X11 window of a raw window handle as a GdkWindow.
+fn foreign_window(handle: &RawWindowHandle) -> Result<gdk::Window, Error> {
+    let xid = match handle {
+        #[cfg(any(
+            target_os = "linux",
+            target_os = "dragonfly",
+            target_os = "freebsd",
+            target_os = "netbsd",
+            target_os = "openbsd"
+        ))]
+        RawWindowHandle::X11(ref handle) if handle.window != 0 => handle.window,
+        _ => {
+            return Err(Error::InvalidParent(
+                "WebKitGTK can only be put in X11 windows".to_owned(),
+            ))
+        }
+    };
+    let display = gdk::Display::default()
+        .filter(|display| display.type_().name() == "GdkX11Display")
+        .ok_or_else(|| {
+            let reason = "GTK isn’t using X11, so it can’t use an X11 parent";
+            Error::InvalidParent(reason.to_owned())
+        })?;
+    extern "C" {
+        // From gdk/x11/gdkx11window.h, which the gdk crate doesn’t cover.
+        fn gdk_x11_window_foreign_new_for_display(
+            display: *mut gdk::ffi::GdkDisplay,
+            window: std::os::raw::c_ulong,
+        ) -> *mut gdk::ffi::GdkWindow;
+    }
+    let window = unsafe {
+        let window = gdk_x11_window_foreign_new_for_display(display.to_glib_none().0, xid);
+        Option::<gdk::Window>::from_glib_full(window)
+    };
+    window.ok_or_else(|| Error::InvalidParent(format!("there is no X11 window {:#x}", xid)))
+}
+
+/// GTK must have been initialised, on this thread, before any widgets can be created.
+pub(crate) fn check_available() -> Result<(), Error> {
+    if gtk::is_initialized_main_thread() {
+        Ok(())
+    } else {
+        Err(Error::BackendUnavailable(
+            "GTK has not been initialised on this thread".to_owned(),
+        ))
+    }
+}
+
+impl Default for Process {
+    fn default() -> Process {
+        Process::new()
+    }
+}
+
+#[derive(Clone)]
+pub struct Control {
+    inner: Rc<RefCell<ControlInner>>,
+}
+
+pub struct ControlInner {
+    container_type: ContainerType,
+    web_view: WebKitWebView,
+    // The web view this one was created related to, if any, which can open pages in it.
+    related_view: Option<glib::WeakRef<WebKitWebView>>,
+    schemes: SchemeRegistry,
+    unsupported_settings: Vec<Setting>,
+    // The window made to hold the web view by `Process::create_control_in_window`, if it was.
+    host_window: Option<gtk::Window>,
+}
+
+impl Drop for ControlInner {
+    fn drop(&mut self) {
+        if let Some(host_window) = self.host_window.take() {
+            // Safe, as nothing else has a reference to it that it would be wrong to invalidate.
+            unsafe { host_window.destroy() };
+        }
+    }
+}
+
+impl Control {
+    pub fn focus(&self) -> Result<(), Error> {
+        self.inner.borrow().web_view.grab_focus();
+        Ok(())
+    }
+
+    pub fn resize(
+        &self,
+        position: Option<(i32, i32)>,
+        size: Option<(i32, i32)>,
+    ) -> Result<(), Error> {
+        let inner = self.inner.borrow();
+        if let ContainerType::InFixed(ref fixed) = inner.container_type {
+            if let Some((x, y)) = position {
+                fixed.move_(&inner.web_view, x, y);
+            }
+        }
+        if let Some(ref host_window) = inner.host_window {
+            if let Some((x, y)) = position {
+                host_window.move_(x, y);
+            }
+            if let Some((width, height)) = size {
+                host_window.resize(width, height);
+            }
+        }
+        if let Some((width, height)) = size {
+            inner.web_view.set_size_request(width, height);
+        }
+        inner.web_view.queue_resize();
+        Ok(())
+    }
+
+    /// Returns a future that resolves to this control once it has been created, which with
+    /// WebKitGTK is immediately.
+    pub fn ready(&self) -> Ready {
+        Ready {
+            control: Some(self.clone()),
+        }
+    }
+
+    /// Get the underlying WebKitWebView instance.
+    ///
+    /// This allows you to do more advanced, engine-specific magicks.
+    pub fn get_inner(&self) -> WebKitWebView {
+        self.inner.borrow().web_view.clone()
+    }
+}
+
+/// A future that resolves once a control has been created. See `Control::ready`.
+pub struct Ready {
+    control: Option<Control>,
+}
+
+impl Future for Ready {
+    type Output = Result<Control, Error>;
+
+    fn poll(mut self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Result<Control, Error>> {
+        Poll::Ready(Ok(self
+            .control
+            .take()
+            .expect("Ready polled after completion")))
+    }
+}
+
+impl WebView for Control {
+    fn navigate(&self, url: &str) -> Result<(), Error> {
+        scheme::check_url(url)?;
+        self.inner.borrow().web_view.load_uri(url);
+        Ok(())
+    }
+
+    fn navigate_to_string(&self, html: &str) -> Result<(), Error> {
+        self.inner.borrow().web_view.load_html(html, None);
+        Ok(())
+    }
+
+    fn go_back(&self) -> Result<(), Error> {
+        self.inner.borrow().web_view.go_back();
+        Ok(())
+    }
+
+    fn go_forward(&self) -> Result<(), Error> {
+        self.inner.borrow().web_view.go_forward();
+        Ok(())
+    }
+
+    fn reload(&self) -> Result<(), Error> {
+        self.inner.borrow().web_view.reload();
+        Ok(())
+    }
+
+    fn stop(&self) -> Result<(), Error> {
+        self.inner.borrow().web_view.stop_loading();
+        Ok(())
+    }
+
+    fn can_go_back(&self) -> bool {
+        self.inner.borrow().web_view.can_go_back()
+    }
+
+    fn can_go_forward(&self) -> bool {
+        self.inner.borrow().web_view.can_go_forward()
+    }
+
+    fn url(&self) -> Option<String> {
+        self.inner.borrow().web_view.uri().map(Into::into)
+    }
+
+    fn title(&self) -> String {
+        self.inner
+            .borrow()
+            .web_view
+            .title()
+            .map(Into::into)
+            .unwrap_or_default()
+    }
+
+    fn unsupported_settings(&self) -> Vec<Setting> {
+        self.inner.borrow().unsupported_settings.clone()
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        CAPABILITIES
+    }
+
+    fn zoom(&self) -> f64 {
+        self.inner.borrow().web_view.zoom_level()
+    }
+
+    fn set_zoom(&self, zoom: f64) -> Result<(), Error> {
+        zoom::check_zoom(zoom)?;
+        self.inner.borrow().web_view.set_zoom_level(zoom);
+        Ok(())
+    }
+
+    fn add_zoom_changed<F>(&self, f: F) -> Result<Subscription, Error>
+    where
+        F: FnMut(f64) + 'static,
+    {
+        let web_view = self.get_inner();
+        let f = RefCell::new(f);
+        let handler_id = web_view.connect_zoom_level_notify(move |web_view| {
+            (*f.borrow_mut())(web_view.zoom_level());
+        });
+        Ok(disconnect_on_drop(&web_view, handler_id))
+    }
+
+    fn default_background_color(&self) -> Color {
+        let rgba = self.inner.borrow().web_view.background_color();
+        let channel = |value: f64| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+        Color::rgba(
+            channel(rgba.red()),
+            channel(rgba.green()),
+            channel(rgba.blue()),
+            channel(rgba.alpha()),
+        )
+    }
+
+    fn set_default_background_color(&self, color: Color) -> Result<(), Error> {
+        let channel = |value: u8| f64::from(value) / 255.0;
+        self.inner
+            .borrow()
+            .web_view
+            .set_background_color(&gdk::RGBA::new(
+                channel(color.r),
+                channel(color.g),
+                channel(color.b),
+                channel(color.a),
+            ));
+        Ok(())
+    }
+
+    fn run_script(&self, script: &str) -> Result<(), Error> {
+        self.inner
+            .borrow()
+            .web_view
+            .run_javascript(script, None::<&gio::Cancellable>, |_result| ());
+        Ok(())
+    }
+
+    fn eval<T: DeserializeOwned>(&self, script: &str) -> Eval<T> {
+        let (resolver, eval) = script::channel();
+        self.inner.borrow().web_view.run_javascript(
+            &script::wrap(script),
+            None::<&gio::Cancellable>,
+            move |result| {
+                resolver.resolve_outcome(
+                    result
+                        .map(|result| result.js_value().map(|value| value.to_str().into()))
+                        .map(Option::unwrap_or_default)
+                        .map_err(Into::into),
+                )
+            },
+        );
+        eval
+    }
+
+    fn capture_image(&self, clip: Option<capture::Rect>) -> Capture {
+        let (resolver, capture) = capture::channel(clip);
+        self.inner.borrow().web_view.snapshot(
+            SnapshotRegion::Visible,
+            SnapshotOptions::NONE,
+            None::<&gio::Cancellable>,
+            move |result| resolver.resolve(result.map_err(Into::into).and_then(surface_image)),
+        );
+        capture
+    }
+
+    /// The dialog is modal, so this returns once it has been dismissed.
+    fn print(&self) -> Result<(), Error> {
+        let web_view = self.inner.borrow().web_view.clone();
+        let window = web_view
+            .toplevel()
+            .and_then(|toplevel| toplevel.downcast::<gtk::Window>().ok());
+        PrintOperation::new(&web_view).run_dialog(window.as_ref());
+        Ok(())
+    }
+
+    fn register_scheme<F>(&self, scheme: &str, handler: F) -> Result<(), Error>
+    where
+        F: Fn(&SchemeRequest) -> SchemeResponse + 'static,
+    {
+        let inner = self.inner.borrow();
+        let scheme = scheme.to_ascii_lowercase();
+        let mut schemes = inner.schemes.borrow_mut();
+        let handlers = schemes.entry(scheme.clone()).or_insert_with(|| {
+            let registry = inner.schemes.clone();
+            inner
+                .web_view
+                .context()
+                .expect("web view was created with a context")
+                .register_uri_scheme(&scheme, move |request| {
+                    handle_scheme_request(&registry, request)
+                });
+            Vec::new()
+        });
+        // Drop any earlier handler for this control, and any for controls that have gone.
+        handlers.retain(|(web_view, _)| match web_view.upgrade() {
+            Some(web_view) => web_view != inner.web_view,
+            None => false,
+        });
+        handlers.push((inner.web_view.downgrade(), Rc::new(handler)));
+        Ok(())
+    }
+
+    fn add_init_script(&self, source: &str) -> Result<Subscription, Error> {
+        let user_content_manager = self
+            .inner
+            .borrow()
+            .web_view
+            .user_content_manager()
+            .expect("web view was created with a user content manager");
+        let script = UserScript::new(
+            source,
+            UserContentInjectedFrames::TopFrame,
+            UserScriptInjectionTime::Start,
+            &[],
+            &[],
+        );
+        user_content_manager.add_script(&script);
+        let user_content_manager = user_content_manager.downgrade();
+        Ok(Subscription::new(move || {
+            if let Some(user_content_manager) = user_content_manager.upgrade() {
+                user_content_manager.remove_script(&script);
+            }
+        }))
+    }
+
+    fn add_script_notify<F>(&self, f: F) -> Result<Subscription, Error>
+    where
+        F: FnMut(String) + 'static,
+    {
+        let user_content_manager = self
+            .inner
+            .borrow()
+            .web_view
+            .user_content_manager()
+            .expect("web view was created with a user content manager");
+        // The signal handler need only be Fn, but we want to accept FnMut.
+        let f = RefCell::new(f);
+        let handler_id = user_content_manager.connect_script_message_received(
+            Some("external"),
+            move |_manager, result| {
+                if let Some(value) = result.js_value() {
+                    (*f.borrow_mut())(value.to_str().into());
+                }
+            },
+        );
+        Ok(disconnect_on_drop(&user_content_manager, handler_id))
+    }
+
+    /// The navigation is cancelled by ignoring WebKit’s navigation policy decision, so it never
+    /// starts loading, and no `NavigationCompleted` follows for it.
+    fn add_navigation_starting<F>(&self, f: F) -> Result<Subscription, Error>
+    where
+        F: FnMut(&NavigationStarting) + 'static,
+    {
+        let web_view = self.get_inner();
+        let f = RefCell::new(f);
+        // load-changed only says a navigation has started once it’s too late to cancel it, so
+        // this is decided with the navigation policy instead.
+        let handler_id =
+            web_view.connect_decide_policy(move |_web_view, decision, decision_type| {
+                if decision_type != PolicyDecisionType::NavigationAction {
+                    return false;
+                }
+                let decision = match decision.downcast_ref::<NavigationPolicyDecision>() {
+                    Some(decision) => decision,
+                    None => return false,
+                };
+                let mut action = match decision.navigation_action() {
+                    Some(action) => action,
+                    None => return false,
+                };
+                let url = action
+                    .request()
+                    .and_then(|request| request.uri())
+                    .map(Into::into)
+                    .unwrap_or_default();
+                let event = NavigationStarting::new(url, Some(action.is_redirect()));
+                (*f.borrow_mut())(&event);
+                if event.is_cancelled() {
+                    decision.ignore();
+                    true
+                } else {
+                    // Leave the decision to any other handlers, and then WebKit.
+                    false
+                }
+            });
+        Ok(disconnect_on_drop(&web_view, handler_id))
+    }
+
+    fn add_content_loading<F>(&self, f: F) -> Result<Subscription, Error>
+    where
+        F: FnMut(&ContentLoading) + 'static,
+    {
+        let web_view = self.get_inner();
+        let f = RefCell::new(f);
+        let handler_id = web_view.connect_load_changed(move |web_view, load_event| {
+            if load_event == LoadEvent::Committed {
+                (*f.borrow_mut())(&ContentLoading {
+                    url: uri_of(web_view),
+                });
+            }
+        });
+        Ok(disconnect_on_drop(&web_view, handler_id))
+    }
+
+    fn add_dom_content_loaded<F>(&self, f: F) -> Result<Subscription, Error>
+    where
+        F: FnMut(&DomContentLoaded) + 'static,
+    {
+        let user_content_manager = self
+            .inner
+            .borrow()
+            .web_view
+            .user_content_manager()
+            .expect("web view was created with a user content manager");
+        let f = RefCell::new(f);
+        let handler_id = user_content_manager.connect_script_message_received(
+            Some("domContentLoaded"),
+            move |_manager, result| {
+                if let Some(value) = result.js_value() {
+                    (*f.borrow_mut())(&DomContentLoaded {
+                        url: value.to_str().into(),
+                    });
+                }
+            },
+        );
+        Ok(disconnect_on_drop(&user_content_manager, handler_id))
+    }
+
+    fn add_navigation_completed<F>(&self, f: F) -> Result<Subscription, Error>
+    where
+        F: FnMut(&NavigationCompleted) + 'static,
+    {
+        let web_view = self.get_inner();
+        // load-failed is followed by load-changed with Finished, so the error waits for that.
+        let failure = Rc::new(Cell::new(None));
+        let failure2 = failure.clone();
+        let failed_id = web_view.connect_load_failed(move |_web_view, _load_event, _uri, error| {
+            failure2.set(Some(web_error_kind(error)));
+            // Let WebKit show its error page.
+            false
+        });
+        let f = RefCell::new(f);
+        let changed_id = web_view.connect_load_changed(move |web_view, load_event| {
+            if load_event != LoadEvent::Finished {
+                return;
+            }
+            let url = uri_of(web_view);
+            let event = match failure.take() {
+                Some(error) => NavigationCompleted {
+                    url,
+                    is_success: false,
+                    http_status: None,
+                    error: Some(error),
+                },
+                None => {
+                    let http_status = web_view
+                        .main_resource()
+                        .and_then(|resource| resource.response())
+                        .map(|response| response.status_code())
+                        // Zero for things that aren’t HTTP, like file: URLs.
+                        .filter(|&status| status != 0)
+                        .map(|status| status as u16);
+                    let is_success = http_status.map_or(true, |status| status < 400);
+                    NavigationCompleted {
+                        url,
+                        is_success,
+                        http_status,
+                        error: if is_success {
+                            None
+                        } else {
+                            Some(WebErrorKind::Http)
+                        },
+                    }
+                }
+            };
+            (*f.borrow_mut())(&event);
+        });
+        let web_view = web_view.downgrade();
+        Ok(Subscription::new(move || {
+            if let Some(web_view) = web_view.upgrade() {
+                web_view.disconnect(failed_id);
+                web_view.disconnect(changed_id);
+            }
+        }))
+    }
+
+    fn add_new_window_requested<F>(&self, f: F) -> Result<Subscription, Error>
+    where
+        F: FnMut(&NewWindowRequested) + 'static,
+    {
+        let web_view = self.get_inner();
+        let f = RefCell::new(f);
+        let handler_id = web_view.connect_create(move |web_view, navigation_action| {
+            let request = navigation_action.request()?;
+            let url: String = request.uri()?.into();
+            let referrer = request
+                .http_headers()
+                .and_then(|headers| headers.one("Referer"))
+                .map(Into::into);
+            let event = NewWindowRequested::new(url.clone(), referrer);
+            (*f.borrow_mut())(&event);
+            match event.take_action()? {
+                NewWindowAction::Ignore => None,
+                NewWindowAction::NavigateHere => {
+                    web_view.load_uri(&url);
+                    None
+                }
+                NewWindowAction::Open(WebViewControl::GtkWebkit2(control)) => {
+                    let new_web_view = control.get_inner();
+                    let related = control.inner.borrow().related_view.clone();
+                    if related.and_then(|view| view.upgrade()).as_ref() == Some(web_view) {
+                        // WebKit loads the page into the web view we return.
+                        Some(new_web_view.upcast())
+                    } else {
+                        new_web_view.load_uri(&url);
+                        None
+                    }
+                }
+                #[allow(unreachable_patterns)]
+                NewWindowAction::Open(control) => {
+                    let _ = control.navigate(&url);
+                    None
+                }
+            }
+        });
+        Ok(disconnect_on_drop(&web_view, handler_id))
+    }
+
+    fn add_permission_requested<F>(&self, f: F) -> Result<Subscription, Error>
+    where
+        F: FnMut(&PermissionRequest) + 'static,
+    {
+        let web_view = self.get_inner();
+        let f = RefCell::new(f);
+        let handler_id = web_view.connect_permission_request(move |web_view, request| {
+            let url = uri_of(web_view);
+            let origin = navigation::origin_of(&url).unwrap_or(url);
+            // Holding a reference to the request keeps it pending until it’s answered.
+            let deferred = request.clone();
+            let event = PermissionRequest::new(permission_kind(request), origin, move || {
+                move |allow| {
+                    if allow {
+                        deferred.allow();
+                    } else {
+                        deferred.deny();
+                    }
+                }
+            });
+            (*f.borrow_mut())(&event);
+            match event.state() {
+                PermissionState::Unanswered => return false,
+                PermissionState::Allowed => request.allow(),
+                PermissionState::Denied => request.deny(),
+                PermissionState::Deferred => (),
+            }
+            true
+        });
+        Ok(disconnect_on_drop(&web_view, handler_id))
+    }
+
+    /// WebKitGTK can only intercept requests in the web process, so this always fails.
+    fn add_web_resource_requested<F>(&self, _f: F) -> Result<Subscription, Error>
+    where
+        F: FnMut(&WebResourceRequest) + 'static,
+    {
+        self.capabilities()
+            .require(Capability::ResourceInterception)?;
+        unreachable!("WebKitGTK’s capabilities don’t include resource interception")
+    }
+}
+
+/// A control filling a GTK window, as `edge_winit::WinitWebView` is for winit.
+///
+/// GTK already keeps focus, size and scale in step with the window, so this only has closing to
+/// see to: closing the window asks the page first, through its `beforeunload` handlers, and WebKit
+/// shows its own prompt if the page wants to stay. The window is destroyed only if the page lets
+/// it close, so quit on the window’s `destroy` rather than on `delete-event`.
+pub struct WindowWebView {
+    control: Control,
+    _delete_event: Subscription,
+    _close: Subscription,
+}
+
+impl WindowWebView {
+    /// Create a control filling the window.
+    pub fn new(
+        process: &Process,
+        window: &gtk::Window,
+        settings: &ControlSettings,
+    ) -> Result<WindowWebView, Error> {
+        let control = process.create_control(
+            ContainerType::FillContainer(window.clone().upcast()),
+            (0, 0),
+            window.size(),
+            settings,
+        )?;
+        let web_view = control.get_inner();
+        let close_approved = Rc::new(Cell::new(false));
+
+        let delete_event = {
+            let close_approved = close_approved.clone();
+            let web_view = web_view.downgrade();
+            window.connect_delete_event(move |_, _| {
+                match web_view.upgrade() {
+                    Some(ref web_view) if !close_approved.get() => {
+                        // This raises close once the page has agreed.
+                        web_view.try_close();
+                        glib::Propagation::Stop
+                    }
+                    _ => glib::Propagation::Proceed,
+                }
+            })
+        };
+        let close = {
+            let window = window.downgrade();
+            web_view.connect_close(move |_| {
+                close_approved.set(true);
+                if let Some(window) = window.upgrade() {
+                    // Not now, as this may be within the delete-event handler.
+                    glib::idle_add_local_once(move || window.close());
+                }
+            })
+        };
+
+        Ok(WindowWebView {
+            _delete_event: disconnect_on_drop(window, delete_event),
+            _close: disconnect_on_drop(&web_view, close),
+            control,
+        })
+    }
+
+    pub fn control(&self) -> &Control {
+        &self.control
+    }
+}
+
+/// A subscription that disconnects the signal handler, if the object is still around.
+fn disconnect_on_drop<O: IsA<glib::Object>>(
+    object: &O,
+    handler_id: glib::SignalHandlerId,
+) -> Subscription {
+    let object = object.downgrade();
+    Subscription::new(move || {
+        if let Some(object) = object.upgrade() {
+            object.disconnect(handler_id);
+        }
+    })
+}
+
+fn uri_of(web_view: &WebKitWebView) -> String {
+    web_view.uri().map(Into::into).unwrap_or_default()
+}
+
+/// Read the pixels of a snapshot, which WebKit makes as an ARGB32 image surface.
+fn surface_image(surface: gtk::cairo::Surface) -> Result<Image, Error> {
+    let failed = |message: &str| {
+        Error::Glib(glib::Error::new(
+            gio::IOErrorEnum::Failed,
+            &format!("could not read the snapshot: {}", message),
+        ))
+    };
+    let surface =
+        gtk::cairo::ImageSurface::try_from(surface).map_err(|_| failed("not an image surface"))?;
+    if surface.format() != gtk::cairo::Format::ARgb32 {
+        return Err(failed("not in ARGB32 format"));
+    }
+    let mut image = None;
+    surface
+        .with_data(|data| {
+            image = Some(Image::from_premultiplied_argb(
+                surface.width() as u32,
+                surface.height() as u32,
+                surface.stride() as usize,
+                data,
+            ));
+        })
+        .map_err(|error| failed(&error.to_string()))?;
+    image.ok_or_else(|| failed("no data"))
+}
+
+fn permission_kind(request: &webkit2gtk::PermissionRequest) -> PermissionKind {
+    if request.is::<GeolocationPermissionRequest>() {
+        PermissionKind::Geolocation
+    } else if let Some(request) = request.downcast_ref::<UserMediaPermissionRequest>() {
+        // A request for neither a camera nor a microphone is for the screen.
+        if request.is_for_audio_device() || request.is_for_video_device() {
+            PermissionKind::Media
+        } else {
+            PermissionKind::ScreenCapture
+        }
+    } else if request.is::<NotificationPermissionRequest>() {
+        PermissionKind::Notifications
+    } else if request.is::<PointerLockPermissionRequest>() {
+        PermissionKind::PointerLock
+    } else if request.is::<WebsiteDataAccessPermissionRequest>() {
+        PermissionKind::Storage
+    } else if request.is::<MediaKeySystemPermissionRequest>() {
+        PermissionKind::Other("media key system".to_owned())
+    } else {
+        PermissionKind::Other(request.type_().name().to_owned())
+    }
+}
+
+fn web_error_kind(error: &glib::Error) -> WebErrorKind {
+    if let Some(kind) = error.kind::<NetworkError>() {
+        if kind == NetworkError::Cancelled {
+            return WebErrorKind::Cancelled;
+        }
+    } else if error.matches(PolicyError::FrameLoadInterruptedByPolicyChange) {
+        return WebErrorKind::Cancelled;
+    } else if let Some(kind) = error.kind::<gio::ResolverError>() {
+        if kind == gio::ResolverError::NotFound || kind == gio::ResolverError::TemporaryFailure {
+            return WebErrorKind::HostNotFound;
+        }
+    } else if error.kind::<gio::TlsError>().is_some() {
+        return WebErrorKind::Certificate;
+    } else if let Some(kind) = error.kind::<gio::IOErrorEnum>() {
+        match kind {
+            gio::IOErrorEnum::Cancelled => return WebErrorKind::Cancelled,
+            gio::IOErrorEnum::TimedOut => return WebErrorKind::Timeout,
+            gio::IOErrorEnum::ConnectionRefused
+            | gio::IOErrorEnum::HostUnreachable
+            | gio::IOErrorEnum::NetworkUnreachable => return WebErrorKind::CannotConnect,
+            gio::IOErrorEnum::BrokenPipe => return WebErrorKind::ConnectionLost,
+            _ => {}
+        }
+    }
+    WebErrorKind::Other(error.message().to_owned())
+}
+
+fn handle_scheme_request(registry: &SchemeRegistry, request: &URISchemeRequest) {
+    let handler = request.web_view().and_then(|web_view| {
+        let scheme = request.scheme()?.to_ascii_lowercase();
+        registry
+            .borrow()
+            .get(&scheme)?
+            .iter()
+            .find(|(handler_web_view, _)| handler_web_view.upgrade().as_ref() == Some(&web_view))
+            .map(|(_, handler)| handler.clone())
+    });
+    let handler = match handler {
+        Some(handler) => handler,
+        None => {
+            request.finish_error(&mut glib::Error::new(
+                gio::IOErrorEnum::NotFound,
+                "this scheme has no handler for this web view",
+            ));
+            return;
+        }
+    };
+
+    let mut headers = Vec::new();
+    if let Some(request_headers) = request.http_headers() {
+        request_headers.foreach(|name, value| headers.push((name.to_owned(), value.to_owned())));
+    }
+    let response = handler(&SchemeRequest {
+        method: request
+            .http_method()
+            .map(Into::into)
+            .unwrap_or_else(|| "GET".to_owned()),
+        uri: request.uri().map(Into::into).unwrap_or_default(),
+        path: request.path().map(Into::into).unwrap_or_default(),
+        headers,
+    });
+
+    let content_type = response.header("Content-Type").map(str::to_owned);
+    let (stream, length): (gio::InputStream, i64) = match response.body {
+        Body::Bytes(Cow::Borrowed(bytes)) => (
+            gio::MemoryInputStream::from_bytes(&glib::Bytes::from_static(bytes)).upcast(),
+            bytes.len() as i64,
+        ),
+        Body::Bytes(Cow::Owned(bytes)) => {
+            let length = bytes.len() as i64;
+            (
+                gio::MemoryInputStream::from_bytes(&glib::Bytes::from_owned(bytes)).upcast(),
+                length,
+            )
+        }
+        // -1 for an unknown length.
+        Body::Reader(reader) => (gio::ReadInputStream::new(reader).upcast(), -1),
+    };
+    let scheme_response = URISchemeResponse::new(&stream, length);
+    scheme_response.set_status(response.status.into(), None);
+    let response_headers = soup::MessageHeaders::new(soup::MessageHeadersType::Response);
+    for (name, value) in &response.headers {
+        response_headers.append(name, value);
+    }
+    scheme_response.set_http_headers(response_headers);
+    if let Some(content_type) = content_type {
+        scheme_response.set_content_type(&content_type);
+    }
+    request.finish_with_response(&scheme_response);
 }
@@ -1,21 +1,299 @@
 //! Experimentation ground. Fear not, more platforms will be added and a consistent API added
 //! before it’s done.
 
+use std::fmt;
+
+use raw_window_handle::{HasRawWindowHandle, RawWindowHandle};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
 pub mod error;
 pub use error::Error;
 
+mod script;
+pub use script::Eval;
+
+pub mod rpc;
+
+mod subscription;
+pub use subscription::Subscription;
+
+mod zoom;
+
+pub mod scheme;
+use scheme::{SchemeRequest, SchemeResponse};
+
+pub mod assets;
+use assets::Assets;
+
+pub mod capture;
+use capture::Capture;
+
+pub mod capabilities;
+use capabilities::Capabilities;
+
+pub mod navigation;
+use navigation::{
+    ContentLoading, DomContentLoaded, NavigationCompleted, NavigationStarting, NewWindowRequested,
+};
+
+pub mod permission;
+use permission::PermissionRequest;
+
+pub mod resource;
+use resource::WebResourceRequest;
+
+pub mod settings;
+use settings::{ControlSettings, Setting};
+
 #[cfg(all(windows, any(feature = "edgehtml", feature = "mshtml")))]
 mod windows;
 
+#[cfg(any(all(windows, feature = "edgehtml"), feature = "mock"))]
+mod ready;
+
 #[cfg(all(windows, feature = "edgehtml"))]
 pub mod edge;
 
+#[cfg(all(windows, feature = "edgehtml"))]
+mod edge_scheme;
+
 #[cfg(all(windows, feature = "edgehtml", feature = "winit"))]
 pub mod edge_winit;
 
 #[cfg(all(windows, feature = "mshtml"))]
 pub mod mshtml;
 
+#[cfg(feature = "gtk-webkit2")]
+pub mod gtk;
+
+#[cfg(feature = "mock")]
+pub mod mock;
+
+/// A colour, as 8-bit red, green, blue and alpha channels (not premultiplied).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+impl Color {
+    pub const WHITE: Color = Color::rgb(255, 255, 255);
+    pub const BLACK: Color = Color::rgb(0, 0, 0);
+    pub const TRANSPARENT: Color = Color::rgba(0, 0, 0, 0);
+
+    /// An opaque colour.
+    pub const fn rgb(r: u8, g: u8, b: u8) -> Color {
+        Color { r, g, b, a: 255 }
+    }
+
+    pub const fn rgba(r: u8, g: u8, b: u8, a: u8) -> Color {
+        Color { r, g, b, a }
+    }
+}
+
+/// The operations common to all web view controls, whatever the backend.
+///
+/// Methods that query the control’s state return a neutral value (e.g. `false` or `None`) if the
+/// control isn’t functioning.
+pub trait WebView {
+    /// Navigate to the given URL.
+    fn navigate(&self, url: &str) -> Result<(), Error>;
+
+    /// Load the given HTML as the document, in place of navigating to a URL.
+    fn navigate_to_string(&self, html: &str) -> Result<(), Error>;
+
+    /// Go back a step in the history, if possible.
+    fn go_back(&self) -> Result<(), Error>;
+
+    /// Go forward a step in the history, if possible.
+    fn go_forward(&self) -> Result<(), Error>;
+
+    /// Reload the current page.
+    fn reload(&self) -> Result<(), Error>;
+
+    /// Stop loading the current page.
+    fn stop(&self) -> Result<(), Error>;
+
+    /// Returns true if the control is functioning and `go_back()` can work.
+    fn can_go_back(&self) -> bool;
+
+    /// Returns true if the control is functioning and `go_forward()` can work.
+    fn can_go_forward(&self) -> bool;
+
+    /// The URL of the current page.
+    fn url(&self) -> Option<String>;
+
+    /// The title of the current document; an empty string if it has none.
+    fn title(&self) -> String;
+
+    /// The settings the control was created with that the backend couldn’t honour, and so
+    /// ignored.
+    fn unsupported_settings(&self) -> Vec<Setting>;
+
+    /// What the engine can do. See the `capabilities` module.
+    fn capabilities(&self) -> Capabilities;
+
+    /// The zoom factor of the page, where 1.0 is normal size.
+    ///
+    /// On EdgeHTML, this doesn’t reflect zooming by the user, which the engine doesn’t report.
+    fn zoom(&self) -> f64;
+
+    /// Set the zoom factor of the page, which must be positive and finite (or this fails with
+    /// `Error::InvalidZoom`); 1.0 is normal size.
+    fn set_zoom(&self, zoom: f64) -> Result<(), Error>;
+
+    /// Add a handler for when the zoom factor changes, by `set_zoom` or, where the engine reports
+    /// it, by the user.
+    fn add_zoom_changed<F>(&self, f: F) -> Result<Subscription, Error>
+    where
+        F: FnMut(f64) + 'static;
+
+    /// Stop the user from zooming the page with ctrl+wheel or the keyboard, until the returned
+    /// subscription is dropped. `set_zoom` still works.
+    ///
+    /// This works by cancelling the page’s events, with an initialization script where the engine
+    /// can remove one (see `Capabilities::init_script_removal`); elsewhere, i.e. EdgeHTML, the
+    /// script is run as each page starts loading, so a page can zoom for a moment before that.
+    /// WebKitGTK handles touchpad pinches itself, so there they still zoom.
+    fn disable_user_zoom(&self) -> Result<Subscription, Error>
+    where
+        Self: Clone + 'static,
+    {
+        zoom::disable_user_zoom(self)
+    }
+
+    /// The colour painted behind the page, which shows before it has loaded and wherever it
+    /// doesn’t paint its own background. The default is white.
+    fn default_background_color(&self) -> Color;
+
+    /// Set the colour painted behind the page. With `Color::TRANSPARENT` (or any alpha below 255)
+    /// whatever is behind the control shows through, so it can sit over native content without a
+    /// white flash while the page loads.
+    ///
+    /// The colour can be set before the control has been created, and takes effect as it is. On
+    /// WebKitGTK, transparency needs the window to have an RGBA visual and to be app-paintable.
+    fn set_default_background_color(&self, color: Color) -> Result<(), Error>;
+
+    /// Run a script in the current page, without waiting for it to complete or its result.
+    fn run_script(&self, script: &str) -> Result<(), Error>;
+
+    /// Run a script in the current page, and deserialise its result.
+    ///
+    /// The result is the value of the script’s last statement, as with `eval`, passed through
+    /// `JSON.stringify`; `undefined` becomes `null`. Promises are not waited for. If the script
+    /// throws an exception, the future resolves to `Error::Script`.
+    ///
+    /// ```rust,ignore
+    /// let sum: i32 = control.eval("1 + 2").await?;
+    /// ```
+    fn eval<T: DeserializeOwned>(&self, script: &str) -> Eval<T>;
+
+    /// Call a JavaScript function in the current page with the given arguments, and deserialise
+    /// its result.
+    ///
+    /// `function` is a JavaScript expression for the function, e.g. `"app.setTitle"`. The
+    /// arguments are serialised to JSON; a tuple, slice or other sequence is spread as the
+    /// arguments, `()` (or anything else that serialises to null) means no arguments, and anything
+    /// else is passed as the only argument. To pass a single array or null, wrap it in a
+    /// one-element tuple.
+    ///
+    /// ```rust,ignore
+    /// let greeting: String = control.call("greet", ("world", 3)).await?;
+    /// ```
+    fn call<T: DeserializeOwned, A: Serialize>(&self, function: &str, args: A) -> Eval<T> {
+        match serde_json::to_value(args) {
+            Ok(args) => self.eval(&script::call(function, args)),
+            Err(error) => Eval::failed(error.into()),
+        }
+    }
+
+    /// Capture what the control is showing as an image, cropped to the clip rectangle if there is
+    /// one. See the `capture` module.
+    fn capture_image(&self, clip: Option<capture::Rect>) -> Capture;
+
+    /// Show the print dialog for the current page. Only WebKitGTK supports this.
+    fn print(&self) -> Result<(), Error>;
+
+    /// Add a script to run at the start of every document loaded in the control from now on, before
+    /// any of the document’s own scripts. Scripts run in the order they were added.
+    ///
+    /// This doesn’t run the script in the current document; use `run_script` for that too if need
+    /// be. The script is removed when the returned subscription is dropped, though on EdgeHTML only
+    /// if the control hasn’t finished creation yet; see `edge::Control::add_init_script`.
+    fn add_init_script(&self, source: &str) -> Result<Subscription, Error>;
+
+    /// Serve URLs in the given scheme (e.g. `app`, for `app://…`) with the handler.
+    ///
+    /// Registering a scheme again replaces its handler. See the `scheme` module for details,
+    /// including how this varies between backends.
+    fn register_scheme<F>(&self, scheme: &str, handler: F) -> Result<(), Error>
+    where
+        F: Fn(&SchemeRequest) -> SchemeResponse + 'static;
+
+    /// Serve embedded assets in the given scheme. See the `assets` module.
+    fn serve_assets(&self, scheme: &str, assets: Assets) -> Result<(), Error> {
+        self.register_scheme(scheme, move |request| assets.respond(request))
+    }
+
+    /// Define a function to handle script notifications triggered from JavaScript like this:
+    ///
+    /// ```javascript
+    /// window.external.notify(string)
+    /// ```
+    ///
+    /// The handler is removed when the returned subscription is dropped.
+    fn add_script_notify<F>(&self, f: F) -> Result<Subscription, Error>
+    where
+        F: FnMut(String) + 'static;
+
+    /// Add a handler for when a navigation starts or is redirected, which can cancel it. See the
+    /// `navigation` module for the sequence of events.
+    ///
+    /// The handler is removed when the returned subscription is dropped, as with the rest of these.
+    fn add_navigation_starting<F>(&self, f: F) -> Result<Subscription, Error>
+    where
+        F: FnMut(&NavigationStarting) + 'static;
+
+    /// Add a handler for when the new document of a navigation starts to arrive.
+    fn add_content_loading<F>(&self, f: F) -> Result<Subscription, Error>
+    where
+        F: FnMut(&ContentLoading) + 'static;
+
+    /// Add a handler for when the new document of a navigation has been parsed.
+    fn add_dom_content_loaded<F>(&self, f: F) -> Result<Subscription, Error>
+    where
+        F: FnMut(&DomContentLoaded) + 'static;
+
+    /// Add a handler for when a navigation has finished, successfully or not.
+    fn add_navigation_completed<F>(&self, f: F) -> Result<Subscription, Error>
+    where
+        F: FnMut(&NavigationCompleted) + 'static;
+
+    /// Add a handler for when a page asks to open a URL in a new window, which decides what
+    /// happens instead. See `navigation::NewWindowRequested`.
+    fn add_new_window_requested<F>(&self, f: F) -> Result<Subscription, Error>
+    where
+        F: FnMut(&NewWindowRequested) + 'static;
+
+    /// Add a handler for when a page asks for permission to use something, like the user’s
+    /// location. See the `permission` module.
+    fn add_permission_requested<F>(&self, f: F) -> Result<Subscription, Error>
+    where
+        F: FnMut(&PermissionRequest) + 'static;
+
+    /// Add a handler for requests the page makes for its resources, which can block them, change
+    /// their headers or answer them itself. See the `resource` module; only EdgeHTML supports
+    /// this.
+    fn add_web_resource_requested<F>(&self, f: F) -> Result<Subscription, Error>
+    where
+        F: FnMut(&WebResourceRequest) + 'static;
+}
+
+/// One of the engines a control can be created with, of those enabled at compile time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Backend {
     #[cfg(all(windows, feature = "edgehtml"))]
     EdgeHTML,
@@ -25,8 +303,191 @@ pub enum Backend {
     GtkWebkit2,
     #[cfg(feature = "cocoa")]
     Cocoa,
+    #[cfg(feature = "mock")]
+    Mock,
+}
+
+// With no backends enabled, Backend is uninhabited and the arguments go unused.
+#[allow(unused_variables)]
+impl Backend {
+    /// Every backend enabled at compile time, best first: the native engines, in their order of
+    /// capability, then the mock backend, which is only any use in tests.
+    pub fn preference_order() -> Vec<Backend> {
+        vec![
+            #[cfg(all(windows, feature = "edgehtml"))]
+            Backend::EdgeHTML,
+            #[cfg(feature = "gtk-webkit2")]
+            Backend::GtkWebkit2,
+            #[cfg(feature = "cocoa")]
+            Backend::Cocoa,
+            #[cfg(all(windows, feature = "mshtml"))]
+            Backend::MSHTML,
+            #[cfg(feature = "mock")]
+            Backend::Mock,
+        ]
+    }
+
+    /// The backends that work here and now, best first.
+    pub fn available() -> Vec<Backend> {
+        Backend::preference_order()
+            .into_iter()
+            .filter(|backend| backend.check_available().is_ok())
+            .collect()
+    }
+
+    /// Check whether the backend works here and now, failing with `Error::BackendUnavailable`
+    /// saying why if it doesn’t.
+    pub fn check_available(self) -> Result<(), Error> {
+        match self {
+            #[cfg(all(windows, feature = "edgehtml"))]
+            Backend::EdgeHTML => edge::check_available(),
+            #[cfg(all(windows, feature = "mshtml"))]
+            Backend::MSHTML => Err(not_implemented()),
+            #[cfg(feature = "gtk-webkit2")]
+            Backend::GtkWebkit2 => gtk::check_available(),
+            #[cfg(feature = "cocoa")]
+            Backend::Cocoa => Err(not_implemented()),
+            #[cfg(feature = "mock")]
+            Backend::Mock => Ok(()),
+        }
+    }
+
+    /// Create a control with this backend, in a process of its own, taking its parent from those
+    /// given.
+    fn create_control(
+        self,
+        parent: &mut Parent,
+        position: (i32, i32),
+        size: (i32, i32),
+        settings: &ControlSettings,
+    ) -> Result<WebViewControl, Error> {
+        match self {
+            #[cfg(all(windows, feature = "edgehtml"))]
+            Backend::EdgeHTML => {
+                let process = edge::Process::new();
+                match (parent.hwnd.take(), &parent.window) {
+                    (Some(hwnd_type), _) => {
+                        process.create_control(hwnd_type, position, size, settings)
+                    }
+                    (None, Some(window)) => {
+                        process.create_control_in_raw_window(window, position, size, settings)
+                    }
+                    (None, None) => Err(no_parent()),
+                }
+                .map(WebViewControl::EdgeHTML)
+            }
+            #[cfg(all(windows, feature = "mshtml"))]
+            Backend::MSHTML => Err(not_implemented()),
+            #[cfg(feature = "gtk-webkit2")]
+            Backend::GtkWebkit2 => {
+                let process = gtk::Process::new();
+                match (parent.gtk_container.take(), &parent.window) {
+                    (Some(container_type), _) => {
+                        process.create_control(container_type, position, size, settings)
+                    }
+                    (None, Some(window)) => {
+                        process.create_control_in_raw_window(window, position, size, settings)
+                    }
+                    (None, None) => Err(no_parent()),
+                }
+                .map(WebViewControl::GtkWebkit2)
+            }
+            #[cfg(feature = "cocoa")]
+            Backend::Cocoa => Err(not_implemented()),
+            #[cfg(feature = "mock")]
+            Backend::Mock => mock::Process::new()
+                .create_control(position, size, settings)
+                .map(WebViewControl::Mock),
+        }
+    }
 }
 
+/// Create a control with the first of the backends that `create` succeeds with, or fail with
+/// `Error::NoBackendAvailable` giving each backend’s reason.
+fn create_with_first<F>(backends: Vec<Backend>, mut create: F) -> Result<WebViewControl, Error>
+where
+    F: FnMut(Backend) -> Result<WebViewControl, Error>,
+{
+    let mut failures = Vec::new();
+    for backend in backends {
+        match create(backend) {
+            Ok(control) => return Ok(control),
+            Err(error) => failures.push((backend, error)),
+        }
+    }
+    Err(Error::NoBackendAvailable(failures))
+}
+
+#[cfg(any(all(windows, feature = "mshtml"), feature = "cocoa"))]
+fn not_implemented() -> Error {
+    Error::BackendUnavailable("webviewcontrol doesn’t implement it yet".to_owned())
+}
+
+#[cfg(any(all(windows, feature = "edgehtml"), feature = "gtk-webkit2"))]
+fn no_parent() -> Error {
+    Error::BackendUnavailable("no parent was given for it".to_owned())
+}
+
+// With no backends enabled, Backend is uninhabited and the formatter goes unused.
+#[allow(unused_variables)]
+impl fmt::Display for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            #[cfg(all(windows, feature = "edgehtml"))]
+            Backend::EdgeHTML => f.write_str("EdgeHTML"),
+            #[cfg(all(windows, feature = "mshtml"))]
+            Backend::MSHTML => f.write_str("MSHTML"),
+            #[cfg(feature = "gtk-webkit2")]
+            Backend::GtkWebkit2 => f.write_str("WebKitGTK"),
+            #[cfg(feature = "cocoa")]
+            Backend::Cocoa => f.write_str("Cocoa"),
+            #[cfg(feature = "mock")]
+            Backend::Mock => f.write_str("mock"),
+        }
+    }
+}
+
+/// Where `WebViewControl::new` is to put the control, for each backend that it might use: a
+/// window that any backend can try, or a backend’s own kind of parent, which it prefers. Backends
+/// with no parent are skipped.
+#[derive(Default)]
+pub struct Parent {
+    window: Option<RawWindowHandle>,
+    #[cfg(all(windows, feature = "edgehtml"))]
+    hwnd: Option<edge::HwndType>,
+    #[cfg(feature = "gtk-webkit2")]
+    gtk_container: Option<gtk::ContainerType>,
+}
+
+impl Parent {
+    /// No parents, which only suits the mock backend.
+    pub fn new() -> Parent {
+        Parent::default()
+    }
+
+    /// Set a window from any windowing library, for the backends that can use it; see their
+    /// `Process::create_control_in_window`.
+    pub fn window<W: HasRawWindowHandle>(mut self, window: &W) -> Parent {
+        self.window = Some(window.raw_window_handle());
+        self
+    }
+
+    /// Set the HWND for EdgeHTML.
+    #[cfg(all(windows, feature = "edgehtml"))]
+    pub fn hwnd(mut self, hwnd_type: edge::HwndType) -> Parent {
+        self.hwnd = Some(hwnd_type);
+        self
+    }
+
+    /// Set the GTK container for WebKitGTK.
+    #[cfg(feature = "gtk-webkit2")]
+    pub fn gtk_container(mut self, container_type: gtk::ContainerType) -> Parent {
+        self.gtk_container = Some(container_type);
+        self
+    }
+}
+
+#[derive(Clone)]
 pub enum WebViewControl {
     #[cfg(all(windows, feature = "mshtml"))]
     MSHTML(mshtml::Control),
@@ -36,4 +497,287 @@ pub enum WebViewControl {
     GtkWebkit2(gtk::Control),
     #[cfg(feature = "cocoa")]
     Cocoa(cocoa::Control),
+    #[cfg(feature = "mock")]
+    Mock(mock::Control),
+}
+
+impl WebViewControl {
+    /// Create a control with the best backend that works here: each of
+    /// `Backend::preference_order()` is tried in turn until one succeeds in creating a control.
+    /// If none does, the error is `Error::NoBackendAvailable`, with each backend’s reason.
+    ///
+    /// The control gets a process of its own; to share one between controls, create them with a
+    /// backend’s `Process` instead. Some backends create controls asynchronously, so creation can
+    /// still fail after this has succeeded; that is reported by the backend control’s `ready`.
+    pub fn new(
+        mut parent: Parent,
+        position: (i32, i32),
+        size: (i32, i32),
+        settings: &ControlSettings,
+    ) -> Result<WebViewControl, Error> {
+        create_with_first(Backend::preference_order(), |backend| {
+            backend
+                .check_available()
+                .and_then(|()| backend.create_control(&mut parent, position, size, settings))
+        })
+    }
+
+    /// The backend the control was created with.
+    pub fn backend(&self) -> Backend {
+        match *self {
+            #[cfg(all(windows, feature = "mshtml"))]
+            WebViewControl::MSHTML(_) => Backend::MSHTML,
+            #[cfg(all(windows, feature = "edgehtml"))]
+            WebViewControl::EdgeHTML(_) => Backend::EdgeHTML,
+            #[cfg(feature = "gtk-webkit2")]
+            WebViewControl::GtkWebkit2(_) => Backend::GtkWebkit2,
+            #[cfg(feature = "cocoa")]
+            WebViewControl::Cocoa(_) => Backend::Cocoa,
+            #[cfg(feature = "mock")]
+            WebViewControl::Mock(_) => Backend::Mock,
+        }
+    }
+}
+
+/// Run the expression with `$control` bound to whichever backend’s control this is.
+macro_rules! forward {
+    ($self:ident, $control:ident => $expr:expr) => {
+        match *$self {
+            #[cfg(all(windows, feature = "mshtml"))]
+            WebViewControl::MSHTML(ref $control) => $expr,
+            #[cfg(all(windows, feature = "edgehtml"))]
+            WebViewControl::EdgeHTML(ref $control) => $expr,
+            #[cfg(feature = "gtk-webkit2")]
+            WebViewControl::GtkWebkit2(ref $control) => $expr,
+            #[cfg(feature = "cocoa")]
+            WebViewControl::Cocoa(ref $control) => $expr,
+            #[cfg(feature = "mock")]
+            WebViewControl::Mock(ref $control) => $expr,
+        }
+    };
+}
+
+// With no backends enabled, WebViewControl is uninhabited and the arguments go unused.
+#[allow(unused_variables)]
+impl WebView for WebViewControl {
+    fn navigate(&self, url: &str) -> Result<(), Error> {
+        forward!(self, control => control.navigate(url))
+    }
+
+    fn navigate_to_string(&self, html: &str) -> Result<(), Error> {
+        forward!(self, control => control.navigate_to_string(html))
+    }
+
+    fn go_back(&self) -> Result<(), Error> {
+        forward!(self, control => control.go_back())
+    }
+
+    fn go_forward(&self) -> Result<(), Error> {
+        forward!(self, control => control.go_forward())
+    }
+
+    fn reload(&self) -> Result<(), Error> {
+        forward!(self, control => control.reload())
+    }
+
+    fn stop(&self) -> Result<(), Error> {
+        forward!(self, control => control.stop())
+    }
+
+    fn can_go_back(&self) -> bool {
+        forward!(self, control => control.can_go_back())
+    }
+
+    fn can_go_forward(&self) -> bool {
+        forward!(self, control => control.can_go_forward())
+    }
+
+    fn url(&self) -> Option<String> {
+        forward!(self, control => control.url())
+    }
+
+    fn title(&self) -> String {
+        forward!(self, control => control.title())
+    }
+
+    fn unsupported_settings(&self) -> Vec<Setting> {
+        forward!(self, control => control.unsupported_settings())
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        forward!(self, control => control.capabilities())
+    }
+
+    fn zoom(&self) -> f64 {
+        forward!(self, control => control.zoom())
+    }
+
+    fn set_zoom(&self, zoom: f64) -> Result<(), Error> {
+        forward!(self, control => control.set_zoom(zoom))
+    }
+
+    fn add_zoom_changed<F>(&self, f: F) -> Result<Subscription, Error>
+    where
+        F: FnMut(f64) + 'static,
+    {
+        forward!(self, control => control.add_zoom_changed(f))
+    }
+
+    fn default_background_color(&self) -> Color {
+        forward!(self, control => control.default_background_color())
+    }
+
+    fn set_default_background_color(&self, color: Color) -> Result<(), Error> {
+        forward!(self, control => control.set_default_background_color(color))
+    }
+
+    fn run_script(&self, script: &str) -> Result<(), Error> {
+        forward!(self, control => control.run_script(script))
+    }
+
+    fn eval<T: DeserializeOwned>(&self, script: &str) -> Eval<T> {
+        forward!(self, control => control.eval(script))
+    }
+
+    fn capture_image(&self, clip: Option<capture::Rect>) -> Capture {
+        forward!(self, control => control.capture_image(clip))
+    }
+
+    fn print(&self) -> Result<(), Error> {
+        forward!(self, control => control.print())
+    }
+
+    fn add_init_script(&self, source: &str) -> Result<Subscription, Error> {
+        forward!(self, control => control.add_init_script(source))
+    }
+
+    fn register_scheme<F>(&self, scheme: &str, handler: F) -> Result<(), Error>
+    where
+        F: Fn(&SchemeRequest) -> SchemeResponse + 'static,
+    {
+        forward!(self, control => control.register_scheme(scheme, handler))
+    }
+
+    fn add_script_notify<F>(&self, f: F) -> Result<Subscription, Error>
+    where
+        F: FnMut(String) + 'static,
+    {
+        forward!(self, control => control.add_script_notify(f))
+    }
+
+    fn add_navigation_starting<F>(&self, f: F) -> Result<Subscription, Error>
+    where
+        F: FnMut(&NavigationStarting) + 'static,
+    {
+        forward!(self, control => control.add_navigation_starting(f))
+    }
+
+    fn add_content_loading<F>(&self, f: F) -> Result<Subscription, Error>
+    where
+        F: FnMut(&ContentLoading) + 'static,
+    {
+        forward!(self, control => control.add_content_loading(f))
+    }
+
+    fn add_dom_content_loaded<F>(&self, f: F) -> Result<Subscription, Error>
+    where
+        F: FnMut(&DomContentLoaded) + 'static,
+    {
+        forward!(self, control => control.add_dom_content_loaded(f))
+    }
+
+    fn add_navigation_completed<F>(&self, f: F) -> Result<Subscription, Error>
+    where
+        F: FnMut(&NavigationCompleted) + 'static,
+    {
+        forward!(self, control => control.add_navigation_completed(f))
+    }
+
+    fn add_new_window_requested<F>(&self, f: F) -> Result<Subscription, Error>
+    where
+        F: FnMut(&NewWindowRequested) + 'static,
+    {
+        forward!(self, control => control.add_new_window_requested(f))
+    }
+
+    fn add_permission_requested<F>(&self, f: F) -> Result<Subscription, Error>
+    where
+        F: FnMut(&PermissionRequest) + 'static,
+    {
+        forward!(self, control => control.add_permission_requested(f))
+    }
+
+    fn add_web_resource_requested<F>(&self, f: F) -> Result<Subscription, Error>
+    where
+        F: FnMut(&WebResourceRequest) + 'static,
+    {
+        forward!(self, control => control.add_web_resource_requested(f))
+    }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use super::*;
+
+    fn unavailable() -> Error {
+        Error::BackendUnavailable("not installed".to_owned())
+    }
+
+    #[test]
+    fn the_mock_backend_is_always_available() {
+        assert_eq!(Backend::preference_order().last(), Some(&Backend::Mock));
+        assert!(Backend::available().contains(&Backend::Mock));
+        assert!(Backend::Mock.check_available().is_ok());
+    }
+
+    #[test]
+    fn new_falls_back_through_the_preference_order() {
+        // Nothing but the mock backend can do without a parent, so any others fail first.
+        let control = WebViewControl::new(
+            Parent::new(),
+            (0, 0),
+            (800, 600),
+            &ControlSettings::default(),
+        )
+        .unwrap();
+        assert_eq!(control.backend(), Backend::Mock);
+
+        let mut tried = 0;
+        let control = create_with_first(vec![Backend::Mock, Backend::Mock], |_backend| {
+            tried += 1;
+            if tried == 1 {
+                return Err(unavailable());
+            }
+            mock::Process::new()
+                .create_control((0, 0), (800, 600), &ControlSettings::default())
+                .map(WebViewControl::Mock)
+        })
+        .unwrap();
+        assert_eq!(control.backend(), Backend::Mock);
+        assert_eq!(tried, 2);
+    }
+
+    #[test]
+    fn no_backend_available_says_why_each_failed() {
+        let error = create_with_first(vec![Backend::Mock, Backend::Mock], |_backend| {
+            Err(unavailable())
+        })
+        .err()
+        .unwrap();
+        match error {
+            Error::NoBackendAvailable(ref failures) => {
+                assert_eq!(failures.len(), 2);
+                for (backend, error) in failures {
+                    assert_eq!(*backend, Backend::Mock);
+                    assert_eq!(error.to_string(), unavailable().to_string());
+                }
+            }
+            ref error => panic!("expected NoBackendAvailable, got {:?}", error),
+        }
+
+        match create_with_first(Vec::new(), |_backend| Err(unavailable())) {
+            Err(Error::NoBackendAvailable(failures)) => assert!(failures.is_empty()),
+            _ => panic!("expected NoBackendAvailable"),
+        }
+    }
 }
@@ -0,0 +1,1235 @@
+//! A headless control that records what is done to it instead of driving a browser engine.
+//!
+//! This is for testing application logic built on this crate without a real engine (e.g. on CI
+//! machines without Windows or a display). It behaves like the EdgeHTML backend as far as is
+//! practical: creation is asynchronous, and only completes when the test says so with
+//! `Control::complete_creation` (or `Control::fail_creation`); until then, operations are queued
+//! and then replayed in order, and `Control::ready()` is pending, just as with EdgeHTML.
+//!
+//! Everything the control is asked to do is recorded, and can be inspected with methods like
+//! `navigations()`; and the test can pretend to be the page with methods like
+//! `inject_script_notify()` and `inject_request()`. Scripts passed to `eval` (and `call`) are not
+//! run, but wait for the test to supply their result with `resolve_script()` or
+//! `throw_from_script()`.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::future::Future;
+use std::mem;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::capabilities::{Capabilities, Capability};
+use crate::capture::{self, Capture, Image};
+use crate::error::Error;
+use crate::navigation::{
+    self, ContentLoading, DomContentLoaded, NavigationCompleted, NavigationStarting,
+    NewWindowAction, NewWindowRequested, WebErrorKind,
+};
+use crate::permission::{PermissionKind, PermissionRequest, PermissionState};
+use crate::ready::Readiness;
+use crate::resource::{ResourceAction, WebResourceRequest};
+use crate::scheme::{self, SchemeHandler, SchemeRequest, SchemeResponse};
+use crate::script::{self, Eval, Resolver};
+use crate::settings::{ControlSettings, Setting};
+use crate::subscription::Subscription;
+use crate::zoom;
+use crate::{Color, WebView, WebViewControl};
+
+/// The position and size passed to a `resize` call.
+pub type Resize = (Option<(i32, i32)>, Option<(i32, i32)>);
+
+/// What became of a request passed to `inject_resource_request`.
+#[derive(Debug)]
+pub enum ResourceOutcome {
+    /// The request would be made, with these headers.
+    Sent(Vec<(String, String)>),
+    Blocked,
+    /// A handler answered the request itself.
+    Responded(SchemeResponse),
+}
+
+/// Event handlers, each with an id, so that its subscription can remove it.
+type Handlers<H> = Vec<(usize, Rc<RefCell<H>>)>;
+
+/// An operation issued before the control was created, to be run once it has been.
+type QueuedOperation = Box<dyn FnOnce(&mut ControlInner)>;
+
+#[derive(Clone, Default)]
+pub struct Process {
+    // The settings its controls can honour, or None for all of them.
+    supported_settings: Option<Vec<Setting>>,
+}
+
+impl Process {
+    pub fn new() -> Process {
+        Process {
+            supported_settings: None,
+        }
+    }
+
+    /// A process whose controls can only honour the given settings, and report any others they
+    /// are created with from `unsupported_settings`, as a real engine would.
+    pub fn with_supported_settings(supported: &[Setting]) -> Process {
+        Process {
+            supported_settings: Some(supported.to_vec()),
+        }
+    }
+
+    /// Create a control. It stays pending until `Control::complete_creation` or
+    /// `Control::fail_creation` is called.
+    ///
+    /// Unless the process says otherwise, every setting is supported, in that it is recorded for
+    /// `Control::settings`.
+    pub fn create_control(
+        &self,
+        position: (i32, i32),
+        size: (i32, i32),
+        settings: &ControlSettings,
+    ) -> Result<Control, Error> {
+        Ok(Control {
+            inner: Rc::new(RefCell::new(ControlInner {
+                readiness: Readiness::pending(),
+                position,
+                size,
+                settings: settings.clone(),
+                unsupported_settings: match self.supported_settings {
+                    Some(ref supported) => settings.unsupported(supported),
+                    None => Vec::new(),
+                },
+                capabilities: Capabilities::ALL,
+                history: Vec::new(),
+                history_index: 0,
+                title: String::new(),
+                zoom: 1.0,
+                background_color: Color::WHITE,
+                navigations: Vec::new(),
+                documents: Vec::new(),
+                reload_count: 0,
+                stop_count: 0,
+                resizes: Vec::new(),
+                background_colors: Vec::new(),
+                focus_count: 0,
+                print_count: 0,
+                scripts: Vec::new(),
+                pending_evals: Vec::new(),
+                pending_captures: Vec::new(),
+                init_scripts: Vec::new(),
+                script_notify_handlers: Vec::new(),
+                zoom_changed_handlers: Vec::new(),
+                navigation_starting_handlers: Vec::new(),
+                content_loading_handlers: Vec::new(),
+                dom_content_loaded_handlers: Vec::new(),
+                navigation_completed_handlers: Vec::new(),
+                new_window_requested_handlers: Vec::new(),
+                permission_requested_handlers: Vec::new(),
+                web_resource_requested_handlers: Vec::new(),
+                starting_navigations: Vec::new(),
+                changed_zooms: Vec::new(),
+                next_handler_id: 0,
+                schemes: HashMap::new(),
+                permission_answers: Vec::new(),
+                queued_operations: Vec::new(),
+            })),
+        })
+    }
+}
+
+#[derive(Clone)]
+pub struct Control {
+    inner: Rc<RefCell<ControlInner>>,
+}
+
+pub struct ControlInner {
+    readiness: Readiness,
+
+    position: (i32, i32),
+    size: (i32, i32),
+    settings: ControlSettings,
+    unsupported_settings: Vec<Setting>,
+    capabilities: Capabilities,
+    // The URLs of the session history, and which one is current.
+    history: Vec<String>,
+    history_index: usize,
+    title: String,
+    zoom: f64,
+    // The background colour as last set, whether or not it has taken effect yet.
+    background_color: Color,
+
+    // What has been done to the control, in order.
+    navigations: Vec<String>,
+    documents: Vec<String>,
+    reload_count: usize,
+    stop_count: usize,
+    resizes: Vec<Resize>,
+    background_colors: Vec<Color>,
+    focus_count: usize,
+    print_count: usize,
+    scripts: Vec<String>,
+    // Scripts passed to eval, oldest first, waiting for the test to supply their results.
+    pending_evals: Vec<(String, Resolver)>,
+    // Likewise captures, waiting for the test to supply the image.
+    pending_captures: Vec<capture::Resolver>,
+    // The initialization scripts, in the order they were added, with ids as for the handlers.
+    init_scripts: Vec<(usize, String)>,
+
+    // Rc so that they can be called without holding a borrow on the ControlInner, as a handler
+    // may well want to use the control.
+    script_notify_handlers: Handlers<dyn FnMut(String)>,
+    zoom_changed_handlers: Handlers<dyn FnMut(f64)>,
+    navigation_starting_handlers: Handlers<dyn FnMut(&NavigationStarting)>,
+    content_loading_handlers: Handlers<dyn FnMut(&ContentLoading)>,
+    dom_content_loaded_handlers: Handlers<dyn FnMut(&DomContentLoaded)>,
+    navigation_completed_handlers: Handlers<dyn FnMut(&NavigationCompleted)>,
+    new_window_requested_handlers: Handlers<dyn FnMut(&NewWindowRequested)>,
+    permission_requested_handlers: Handlers<dyn FnMut(&PermissionRequest)>,
+    web_resource_requested_handlers: Handlers<dyn FnMut(&WebResourceRequest)>,
+    // Navigations that have taken effect but not yet raised NavigationStarting, which has to wait
+    // until the ControlInner is no longer borrowed; likewise zoom factors and ZoomChanged.
+    starting_navigations: Vec<String>,
+    changed_zooms: Vec<f64>,
+    next_handler_id: usize,
+
+    // By lowercase scheme.
+    schemes: HashMap<String, Rc<SchemeHandler>>,
+
+    // The answers to the permission requests the test has made, in order.
+    permission_answers: Vec<PermissionState>,
+
+    // As with EdgeHTML, operations issued while the control is loading, in order.
+    queued_operations: Vec<QueuedOperation>,
+}
+
+impl ControlInner {
+    /// Run the operation now if the control has been created, or queue it to be run once it has.
+    ///
+    /// Returns an error if creation has already failed, as the operation can never happen.
+    fn run_or_queue<F>(&mut self, operation: F) -> Result<(), Error>
+    where
+        F: FnOnce(&mut ControlInner) + 'static,
+    {
+        if self.readiness.is_created() {
+            operation(self);
+        } else if self.readiness.has_failed() {
+            return Err(Error::CreationFailed);
+        } else {
+            self.queued_operations.push(Box::new(operation));
+        }
+        Ok(())
+    }
+
+    /// Make the URL the current history entry, discarding any forward history.
+    fn push_history(&mut self, url: &str) {
+        if !self.history.is_empty() {
+            self.history.truncate(self.history_index + 1);
+        }
+        self.history.push(url.to_owned());
+        self.history_index = self.history.len() - 1;
+        self.title.clear();
+    }
+}
+
+impl Control {
+    /// Pretend that the engine has finished creating the control.
+    ///
+    /// This replays any queued operations in order and then wakes anything waiting on `ready()`.
+    /// Does nothing if creation has already completed or failed.
+    pub fn complete_creation(&self) {
+        let mut inner = self.inner.borrow_mut();
+        if inner.readiness.is_created() || inner.readiness.has_failed() {
+            return;
+        }
+        // Mark it created first, so that the replayed operations don’t just queue themselves
+        // again.
+        inner.readiness.set_created(None);
+        for operation in mem::take(&mut inner.queued_operations) {
+            operation(&mut inner);
+        }
+        drop(inner);
+        self.start_navigations();
+        self.raise_changed_zooms();
+    }
+
+    /// Pretend that the engine failed to create the control.
+    ///
+    /// This discards any queued operations and passes the error to the first `ready()` future to
+    /// resolve; any further operations will fail with `Error::CreationFailed`. Does nothing if
+    /// creation has already completed or failed.
+    pub fn fail_creation(&self, error: Error) {
+        let mut inner = self.inner.borrow_mut();
+        if inner.readiness.is_created() || inner.readiness.has_failed() {
+            return;
+        }
+        inner.queued_operations.clear();
+        inner.readiness.set_failed(error);
+    }
+
+    /// Whether `complete_creation` has been called yet.
+    pub fn is_created(&self) -> bool {
+        self.inner.borrow().readiness.is_created()
+    }
+
+    /// Returns a future that resolves to this control once `complete_creation` has been called,
+    /// or to the error passed to `fail_creation`.
+    pub fn ready(&self) -> Ready {
+        Ready {
+            control: self.clone(),
+        }
+    }
+
+    pub fn focus(&self) -> Result<(), Error> {
+        self.run_or_queue(|inner| inner.focus_count += 1)
+    }
+
+    pub fn resize(
+        &self,
+        position: Option<(i32, i32)>,
+        size: Option<(i32, i32)>,
+    ) -> Result<(), Error> {
+        self.run_or_queue(move |inner| {
+            if let Some(position) = position {
+                inner.position = position;
+            }
+            if let Some(size) = size {
+                inner.size = size;
+            }
+            inner.resizes.push((position, size));
+        })
+    }
+
+    /// An id for a handler or initialization script, for its subscription to remove it by.
+    fn next_handler_id(&self) -> usize {
+        let mut inner = self.inner.borrow_mut();
+        inner.next_handler_id += 1;
+        inner.next_handler_id
+    }
+
+    /// Run the operation now if the control has been created, or queue it to be run once it has.
+    fn run_or_queue<F>(&self, operation: F) -> Result<(), Error>
+    where
+        F: FnOnce(&mut ControlInner) + 'static,
+    {
+        self.inner.borrow_mut().run_or_queue(operation)?;
+        self.start_navigations();
+        self.raise_changed_zooms();
+        Ok(())
+    }
+
+    /// Add a handler to one of the lists of handlers, once the control has been created.
+    fn add_handler<H: ?Sized + 'static>(
+        &self,
+        handlers: fn(&mut ControlInner) -> &mut Handlers<H>,
+        handler: Rc<RefCell<H>>,
+    ) -> Result<Subscription, Error> {
+        let id = self.next_handler_id();
+        self.run_or_queue(move |inner| handlers(inner).push((id, handler)))?;
+        let inner = Rc::downgrade(&self.inner);
+        Ok(Subscription::new(move || {
+            if let Some(inner) = inner.upgrade() {
+                // If the handler is still queued, so is this, after it.
+                let _ = inner.borrow_mut().run_or_queue(move |inner| {
+                    handlers(inner).retain(|&(handler_id, _)| handler_id != id)
+                });
+            }
+        }))
+    }
+
+    /// The handlers in one of the lists, to be called without holding a borrow.
+    fn handlers<H: ?Sized>(
+        &self,
+        handlers: fn(&mut ControlInner) -> &mut Handlers<H>,
+    ) -> Vec<Rc<RefCell<H>>> {
+        handlers(&mut self.inner.borrow_mut())
+            .iter()
+            .map(|(_id, handler)| handler.clone())
+            .collect()
+    }
+
+    /// Raise NavigationStarting for the navigations that have taken effect, making each the
+    /// current history entry unless a handler cancels it.
+    fn start_navigations(&self) {
+        loop {
+            let url = {
+                let mut inner = self.inner.borrow_mut();
+                if inner.starting_navigations.is_empty() {
+                    return;
+                }
+                inner.starting_navigations.remove(0)
+            };
+            if self.raise_navigation_starting(&url, false) {
+                self.inner.borrow_mut().push_history(&url);
+            }
+        }
+    }
+
+    /// Raise ZoomChanged for the zoom factors that have taken effect.
+    fn raise_changed_zooms(&self) {
+        let zooms = mem::take(&mut self.inner.borrow_mut().changed_zooms);
+        for zoom in zooms {
+            self.raise_zoom_changed(zoom);
+        }
+    }
+
+    /// Returns false if a handler cancelled the navigation.
+    fn raise_navigation_starting(&self, url: &str, is_redirect: bool) -> bool {
+        let event = NavigationStarting::new(url.to_owned(), Some(is_redirect));
+        for handler in self.handlers(|inner| &mut inner.navigation_starting_handlers) {
+            (*handler.borrow_mut())(&event);
+        }
+        !event.is_cancelled()
+    }
+
+    fn raise_navigation_completed(&self, event: NavigationCompleted) {
+        for handler in self.handlers(|inner| &mut inner.navigation_completed_handlers) {
+            (*handler.borrow_mut())(&event);
+        }
+    }
+
+    // --- Recorded state ---
+
+    /// The current position and size of the control, in physical pixels.
+    pub fn bounds(&self) -> ((i32, i32), (i32, i32)) {
+        let inner = self.inner.borrow();
+        (inner.position, inner.size)
+    }
+
+    /// Every URL passed to `navigate`, in the order they took effect.
+    pub fn navigations(&self) -> Vec<String> {
+        self.inner.borrow().navigations.clone()
+    }
+
+    /// Every HTML document passed to `navigate_to_string`, in the order they took effect.
+    pub fn documents(&self) -> Vec<String> {
+        self.inner.borrow().documents.clone()
+    }
+
+    /// How many times the control has been asked to reload.
+    pub fn reload_count(&self) -> usize {
+        self.inner.borrow().reload_count
+    }
+
+    /// How many times the control has been asked to stop loading.
+    pub fn stop_count(&self) -> usize {
+        self.inner.borrow().stop_count
+    }
+
+    /// The settings the control was created with.
+    pub fn settings(&self) -> ControlSettings {
+        self.inner.borrow().settings.clone()
+    }
+
+    /// Pretend that the engine can only do these things, so that the operations needing the rest
+    /// fail with `Error::Unsupported`. It can do everything to begin with.
+    pub fn set_capabilities(&self, capabilities: Capabilities) {
+        self.inner.borrow_mut().capabilities = capabilities;
+    }
+
+    /// The arguments of every `resize` that took effect, in order.
+    pub fn resizes(&self) -> Vec<Resize> {
+        self.inner.borrow().resizes.clone()
+    }
+
+    /// Every default background colour that took effect, in order.
+    pub fn background_colors(&self) -> Vec<Color> {
+        self.inner.borrow().background_colors.clone()
+    }
+
+    /// How many times the control has been focused.
+    pub fn focus_count(&self) -> usize {
+        self.inner.borrow().focus_count
+    }
+
+    /// How many times the print dialog has been asked for.
+    pub fn print_count(&self) -> usize {
+        self.inner.borrow().print_count
+    }
+
+    /// Every script passed to `run_script`, in the order they took effect.
+    pub fn scripts(&self) -> Vec<String> {
+        self.inner.borrow().scripts.clone()
+    }
+
+    /// The initialization scripts that have been added and not yet removed, in order.
+    ///
+    /// They are not run, as there is no page, but this shows what a page would get.
+    pub fn init_scripts(&self) -> Vec<String> {
+        self.inner
+            .borrow()
+            .init_scripts
+            .iter()
+            .map(|(_id, script)| script.clone())
+            .collect()
+    }
+
+    /// How many script notify handlers have been added and not yet removed.
+    pub fn script_notify_handler_count(&self) -> usize {
+        self.inner.borrow().script_notify_handlers.len()
+    }
+
+    /// The answers to the requests made with `inject_permission_request`, in order. A deferred
+    /// request changes from `Deferred` to `Allowed` or `Denied` once it is answered.
+    pub fn permission_answers(&self) -> Vec<PermissionState> {
+        self.inner.borrow().permission_answers.clone()
+    }
+
+    /// The scripts passed to `eval` (including those generated by `call`) that are still waiting
+    /// for a result, oldest first.
+    pub fn pending_scripts(&self) -> Vec<String> {
+        self.inner
+            .borrow()
+            .pending_evals
+            .iter()
+            .map(|(script, _)| script.clone())
+            .collect()
+    }
+
+    /// How many calls to `capture_image` are still waiting for an image.
+    pub fn pending_capture_count(&self) -> usize {
+        self.inner.borrow().pending_captures.len()
+    }
+
+    // --- Pretending to be the page ---
+
+    /// Complete the oldest pending `eval` with the value, as though the script had evaluated to
+    /// it. Returns false if there was no pending script.
+    pub fn resolve_script(&self, value: impl Serialize) -> bool {
+        self.finish_script(serde_json::to_value(value).map_err(Into::into))
+    }
+
+    /// Complete the oldest pending `eval` as though the script had thrown an exception. Returns
+    /// false if there was no pending script.
+    pub fn throw_from_script(&self, message: &str, stack: Option<&str>) -> bool {
+        self.finish_script(Err(Error::Script {
+            message: message.to_owned(),
+            stack: stack.map(str::to_owned),
+        }))
+    }
+
+    fn finish_script(&self, result: Result<serde_json::Value, Error>) -> bool {
+        let mut inner = self.inner.borrow_mut();
+        if inner.pending_evals.is_empty() {
+            return false;
+        }
+        let (_script, resolver) = inner.pending_evals.remove(0);
+        drop(inner);
+        resolver.resolve(result);
+        true
+    }
+
+    /// Complete the oldest pending `capture_image` with the image, as though it were what the
+    /// control is showing; it is cropped as the capture asked. Returns false if there was no
+    /// pending capture.
+    pub fn finish_capture(&self, image: Image) -> bool {
+        let mut inner = self.inner.borrow_mut();
+        if inner.pending_captures.is_empty() {
+            return false;
+        }
+        let resolver = inner.pending_captures.remove(0);
+        drop(inner);
+        resolver.resolve(Ok(image));
+        true
+    }
+
+    /// Deliver a script notification, as though the page had called `window.external.notify`.
+    pub fn inject_script_notify(&self, value: &str) {
+        for handler in self.handlers(|inner| &mut inner.script_notify_handlers) {
+            (*handler.borrow_mut())(value.to_owned());
+        }
+    }
+
+    /// Request a URL in a custom scheme, as though the page had, returning the handler’s
+    /// response; or `None` if the URL is not in a registered scheme.
+    pub fn inject_request(
+        &self,
+        method: &str,
+        url: &str,
+        headers: &[(&str, &str)],
+    ) -> Option<SchemeResponse> {
+        let (scheme, rest) = scheme::split_url(url)?;
+        let handler = self
+            .inner
+            .borrow()
+            .schemes
+            .get(&scheme.to_ascii_lowercase())?
+            .clone();
+        Some(handler(&SchemeRequest {
+            method: method.to_owned(),
+            uri: url.to_owned(),
+            path: scheme::path_of(rest).to_owned(),
+            headers: headers
+                .iter()
+                .map(|&(name, value)| (name.to_owned(), value.to_owned()))
+                .collect(),
+        }))
+    }
+
+    /// Pretend that the page navigated itself (e.g. by a link or `location.href`) to the URL.
+    ///
+    /// This raises `NavigationStarting`, and makes the URL the current history entry unless a
+    /// handler cancels it. It is not recorded in `navigations()`, which only lists navigations
+    /// the control was asked to make.
+    pub fn inject_navigation(&self, url: &str) {
+        if self.raise_navigation_starting(url, false) {
+            self.inner.borrow_mut().push_history(url);
+        }
+    }
+
+    /// Pretend that the current navigation was redirected to the URL.
+    ///
+    /// This raises `NavigationStarting` as a redirect, and replaces the current history entry
+    /// with the URL unless a handler cancels it.
+    pub fn inject_redirect(&self, url: &str) {
+        if self.raise_navigation_starting(url, true) {
+            let mut inner = self.inner.borrow_mut();
+            let index = inner.history_index;
+            match inner.history.get_mut(index) {
+                Some(entry) => *entry = url.to_owned(),
+                None => inner.push_history(url),
+            }
+        }
+    }
+
+    /// Pretend that the current navigation finished loading, with the given HTTP status.
+    ///
+    /// A status below 400 raises `ContentLoading`, `DomContentLoaded` and a successful
+    /// `NavigationCompleted`; anything else, an unsuccessful `NavigationCompleted` with
+    /// `WebErrorKind::Http`, as EdgeHTML does.
+    pub fn finish_navigation(&self, http_status: u16) {
+        let url = self.url().unwrap_or_default();
+        let is_success = http_status < 400;
+        if is_success {
+            let event = ContentLoading { url: url.clone() };
+            for handler in self.handlers(|inner| &mut inner.content_loading_handlers) {
+                (*handler.borrow_mut())(&event);
+            }
+            let event = DomContentLoaded { url: url.clone() };
+            for handler in self.handlers(|inner| &mut inner.dom_content_loaded_handlers) {
+                (*handler.borrow_mut())(&event);
+            }
+        }
+        self.raise_navigation_completed(NavigationCompleted {
+            url,
+            is_success,
+            http_status: Some(http_status),
+            error: if is_success {
+                None
+            } else {
+                Some(WebErrorKind::Http)
+            },
+        });
+    }
+
+    /// Pretend that the current navigation failed, raising an unsuccessful `NavigationCompleted`.
+    pub fn fail_navigation(&self, error: WebErrorKind) {
+        self.raise_navigation_completed(NavigationCompleted {
+            url: self.url().unwrap_or_default(),
+            is_success: false,
+            http_status: None,
+            error: Some(error),
+        });
+    }
+
+    /// Pretend that the page asked to open the URL in a new window.
+    ///
+    /// If a handler chooses `navigate_here`, this navigates as `inject_navigation` does; if it
+    /// chooses `open_in` with a mock control, that control is navigated to the URL and returned.
+    pub fn inject_new_window_request(&self, url: &str, referrer: Option<&str>) -> Option<Control> {
+        let event = NewWindowRequested::new(url.to_owned(), referrer.map(str::to_owned));
+        for handler in self.handlers(|inner| &mut inner.new_window_requested_handlers) {
+            (*handler.borrow_mut())(&event);
+        }
+        match event.take_action() {
+            None | Some(NewWindowAction::Ignore) => None,
+            Some(NewWindowAction::NavigateHere) => {
+                self.inject_navigation(url);
+                None
+            }
+            Some(NewWindowAction::Open(WebViewControl::Mock(control))) => {
+                let _ = control.navigate(url);
+                Some(control)
+            }
+            #[allow(unreachable_patterns)]
+            Some(NewWindowAction::Open(control)) => {
+                let _ = control.navigate(url);
+                None
+            }
+        }
+    }
+
+    /// Pretend that the page at the URL asked for a permission, returning the index of the request
+    /// in `permission_answers()`.
+    pub fn inject_permission_request(&self, kind: PermissionKind, url: &str) -> usize {
+        let index = {
+            let mut inner = self.inner.borrow_mut();
+            inner.permission_answers.push(PermissionState::Unanswered);
+            inner.permission_answers.len() - 1
+        };
+        let weak_inner = Rc::downgrade(&self.inner);
+        let origin = navigation::origin_of(url).unwrap_or_else(|| url.to_owned());
+        let request = PermissionRequest::new(kind, origin, move || {
+            move |allow| {
+                if let Some(inner) = weak_inner.upgrade() {
+                    inner.borrow_mut().permission_answers[index] = if allow {
+                        PermissionState::Allowed
+                    } else {
+                        PermissionState::Denied
+                    };
+                }
+            }
+        });
+        for handler in self.handlers(|inner| &mut inner.permission_requested_handlers) {
+            (*handler.borrow_mut())(&request);
+        }
+        let state = request.state();
+        // A deferred request may have been answered already, or dropped and so denied.
+        let mut inner = self.inner.borrow_mut();
+        if state != PermissionState::Deferred
+            || inner.permission_answers[index] == PermissionState::Unanswered
+        {
+            inner.permission_answers[index] = state;
+        }
+        index
+    }
+
+    /// Pretend that the page requested a resource, returning what the handlers made of it.
+    pub fn inject_resource_request(
+        &self,
+        method: &str,
+        url: &str,
+        headers: &[(&str, &str)],
+    ) -> ResourceOutcome {
+        let request = WebResourceRequest::new(
+            url.to_owned(),
+            method.to_owned(),
+            headers
+                .iter()
+                .map(|&(name, value)| (name.to_owned(), value.to_owned()))
+                .collect(),
+        );
+        for handler in self.handlers(|inner| &mut inner.web_resource_requested_handlers) {
+            (*handler.borrow_mut())(&request);
+        }
+        match request.take_action() {
+            None => ResourceOutcome::Sent(request.headers()),
+            Some(ResourceAction::Block) => ResourceOutcome::Blocked,
+            Some(ResourceAction::Respond(response)) => ResourceOutcome::Responded(response),
+        }
+    }
+
+    /// Pretend that the page set its title.
+    pub fn inject_title(&self, title: &str) {
+        self.inner.borrow_mut().title = title.to_owned();
+    }
+
+    /// Pretend that the user zoomed the page.
+    pub fn inject_zoom(&self, zoom: f64) {
+        self.inner.borrow_mut().zoom = zoom;
+        self.raise_zoom_changed(zoom);
+    }
+
+    fn raise_zoom_changed(&self, zoom: f64) {
+        for handler in self.handlers(|inner| &mut inner.zoom_changed_handlers) {
+            (*handler.borrow_mut())(zoom);
+        }
+    }
+}
+
+/// A future that resolves once a control has been created. See `Control::ready`.
+pub struct Ready {
+    control: Control,
+}
+
+impl Future for Ready {
+    type Output = Result<Control, Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<Control, Error>> {
+        let control = &self.control;
+        control
+            .inner
+            .borrow_mut()
+            .readiness
+            .poll(cx)
+            .map(|result| result.map(|()| control.clone()))
+    }
+}
+
+impl WebView for Control {
+    fn navigate(&self, url: &str) -> Result<(), Error> {
+        scheme::check_url(url)?;
+        let url = url.to_owned();
+        self.run_or_queue(move |inner| {
+            inner.navigations.push(url.clone());
+            inner.starting_navigations.push(url);
+        })
+    }
+
+    fn navigate_to_string(&self, html: &str) -> Result<(), Error> {
+        let html = html.to_owned();
+        self.run_or_queue(move |inner| {
+            inner.documents.push(html);
+            inner.starting_navigations.push("about:blank".to_owned());
+        })
+    }
+
+    fn go_back(&self) -> Result<(), Error> {
+        self.run_or_queue(|inner| {
+            if inner.history_index > 0 {
+                inner.history_index -= 1;
+                inner.title.clear();
+            }
+        })
+    }
+
+    fn go_forward(&self) -> Result<(), Error> {
+        self.run_or_queue(|inner| {
+            if inner.history_index + 1 < inner.history.len() {
+                inner.history_index += 1;
+                inner.title.clear();
+            }
+        })
+    }
+
+    fn reload(&self) -> Result<(), Error> {
+        self.run_or_queue(|inner| inner.reload_count += 1)
+    }
+
+    fn stop(&self) -> Result<(), Error> {
+        self.run_or_queue(|inner| inner.stop_count += 1)
+    }
+
+    fn can_go_back(&self) -> bool {
+        let inner = self.inner.borrow();
+        inner.readiness.is_created() && inner.history_index > 0
+    }
+
+    fn can_go_forward(&self) -> bool {
+        let inner = self.inner.borrow();
+        inner.readiness.is_created() && inner.history_index + 1 < inner.history.len()
+    }
+
+    fn url(&self) -> Option<String> {
+        let inner = self.inner.borrow();
+        inner.history.get(inner.history_index).cloned()
+    }
+
+    fn title(&self) -> String {
+        self.inner.borrow().title.clone()
+    }
+
+    fn unsupported_settings(&self) -> Vec<Setting> {
+        self.inner.borrow().unsupported_settings.clone()
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        self.inner.borrow().capabilities
+    }
+
+    fn zoom(&self) -> f64 {
+        self.inner.borrow().zoom
+    }
+
+    fn set_zoom(&self, zoom: f64) -> Result<(), Error> {
+        zoom::check_zoom(zoom)?;
+        self.run_or_queue(move |inner| {
+            inner.zoom = zoom;
+            inner.changed_zooms.push(zoom);
+        })
+    }
+
+    fn add_zoom_changed<F>(&self, f: F) -> Result<Subscription, Error>
+    where
+        F: FnMut(f64) + 'static,
+    {
+        self.add_handler::<dyn FnMut(f64)>(
+            |inner| &mut inner.zoom_changed_handlers,
+            Rc::new(RefCell::new(f)),
+        )
+    }
+
+    fn default_background_color(&self) -> Color {
+        self.inner.borrow().background_color
+    }
+
+    fn set_default_background_color(&self, color: Color) -> Result<(), Error> {
+        self.inner.borrow_mut().background_color = color;
+        self.run_or_queue(move |inner| inner.background_colors.push(color))
+    }
+
+    fn run_script(&self, script: &str) -> Result<(), Error> {
+        let script = script.to_owned();
+        self.run_or_queue(move |inner| inner.scripts.push(script))
+    }
+
+    fn eval<T: DeserializeOwned>(&self, script: &str) -> Eval<T> {
+        let (resolver, eval) = script::channel();
+        let script = script.to_owned();
+        // If creation has failed, the resolver is dropped, which fails the eval.
+        let _ = self.run_or_queue(move |inner| inner.pending_evals.push((script, resolver)));
+        eval
+    }
+
+    fn capture_image(&self, clip: Option<capture::Rect>) -> Capture {
+        if let Err(error) = self.capabilities().require(Capability::Capture) {
+            return Capture::failed(error);
+        }
+        let (resolver, capture) = capture::channel(clip);
+        // If creation has failed, the resolver is dropped, which fails the capture.
+        let _ = self.run_or_queue(move |inner| inner.pending_captures.push(resolver));
+        capture
+    }
+
+    fn print(&self) -> Result<(), Error> {
+        self.capabilities().require(Capability::Printing)?;
+        self.run_or_queue(|inner| inner.print_count += 1)
+    }
+
+    fn register_scheme<F>(&self, scheme: &str, handler: F) -> Result<(), Error>
+    where
+        F: Fn(&SchemeRequest) -> SchemeResponse + 'static,
+    {
+        self.capabilities().require(Capability::CustomSchemes)?;
+        let scheme = scheme.to_ascii_lowercase();
+        let handler: Rc<SchemeHandler> = Rc::new(handler);
+        self.run_or_queue(move |inner| {
+            inner.schemes.insert(scheme, handler);
+        })
+    }
+
+    fn add_init_script(&self, source: &str) -> Result<Subscription, Error> {
+        self.capabilities().require(Capability::InitScripts)?;
+        let id = self.next_handler_id();
+        let source = source.to_owned();
+        self.run_or_queue(move |inner| inner.init_scripts.push((id, source)))?;
+        let inner = Rc::downgrade(&self.inner);
+        Ok(Subscription::new(move || {
+            if let Some(inner) = inner.upgrade() {
+                let _ = inner.borrow_mut().run_or_queue(move |inner| {
+                    inner.init_scripts.retain(|&(script_id, _)| script_id != id)
+                });
+            }
+        }))
+    }
+
+    fn add_script_notify<F>(&self, f: F) -> Result<Subscription, Error>
+    where
+        F: FnMut(String) + 'static,
+    {
+        self.add_handler::<dyn FnMut(String)>(
+            |inner| &mut inner.script_notify_handlers,
+            Rc::new(RefCell::new(f)),
+        )
+    }
+
+    fn add_navigation_starting<F>(&self, f: F) -> Result<Subscription, Error>
+    where
+        F: FnMut(&NavigationStarting) + 'static,
+    {
+        self.add_handler::<dyn FnMut(&NavigationStarting)>(
+            |inner| &mut inner.navigation_starting_handlers,
+            Rc::new(RefCell::new(f)),
+        )
+    }
+
+    fn add_content_loading<F>(&self, f: F) -> Result<Subscription, Error>
+    where
+        F: FnMut(&ContentLoading) + 'static,
+    {
+        self.add_handler::<dyn FnMut(&ContentLoading)>(
+            |inner| &mut inner.content_loading_handlers,
+            Rc::new(RefCell::new(f)),
+        )
+    }
+
+    fn add_dom_content_loaded<F>(&self, f: F) -> Result<Subscription, Error>
+    where
+        F: FnMut(&DomContentLoaded) + 'static,
+    {
+        self.add_handler::<dyn FnMut(&DomContentLoaded)>(
+            |inner| &mut inner.dom_content_loaded_handlers,
+            Rc::new(RefCell::new(f)),
+        )
+    }
+
+    fn add_navigation_completed<F>(&self, f: F) -> Result<Subscription, Error>
+    where
+        F: FnMut(&NavigationCompleted) + 'static,
+    {
+        self.add_handler::<dyn FnMut(&NavigationCompleted)>(
+            |inner| &mut inner.navigation_completed_handlers,
+            Rc::new(RefCell::new(f)),
+        )
+    }
+
+    fn add_new_window_requested<F>(&self, f: F) -> Result<Subscription, Error>
+    where
+        F: FnMut(&NewWindowRequested) + 'static,
+    {
+        self.add_handler::<dyn FnMut(&NewWindowRequested)>(
+            |inner| &mut inner.new_window_requested_handlers,
+            Rc::new(RefCell::new(f)),
+        )
+    }
+
+    fn add_permission_requested<F>(&self, f: F) -> Result<Subscription, Error>
+    where
+        F: FnMut(&PermissionRequest) + 'static,
+    {
+        self.add_handler::<dyn FnMut(&PermissionRequest)>(
+            |inner| &mut inner.permission_requested_handlers,
+            Rc::new(RefCell::new(f)),
+        )
+    }
+
+    fn add_web_resource_requested<F>(&self, f: F) -> Result<Subscription, Error>
+    where
+        F: FnMut(&WebResourceRequest) + 'static,
+    {
+        self.capabilities()
+            .require(Capability::ResourceInterception)?;
+        self.add_handler::<dyn FnMut(&WebResourceRequest)>(
+            |inner| &mut inner.web_resource_requested_handlers,
+            Rc::new(RefCell::new(f)),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn control() -> Control {
+        Process::new()
+            .create_control((0, 0), (800, 600), &ControlSettings::default())
+            .unwrap()
+    }
+
+    #[test]
+    fn operations_queue_until_creation_and_replay_in_order() {
+        let control = control();
+        control.navigate("https://example.com/").unwrap();
+        control.set_zoom(2.0).unwrap();
+        control.navigate("https://example.com/two").unwrap();
+        control.resize(None, Some((400, 300))).unwrap();
+        let mut eval = control.eval::<i32>("1 + 2");
+        assert!(control.navigations().is_empty());
+        assert!(control.pending_scripts().is_empty());
+        assert_eq!(control.zoom(), 1.0);
+        assert_eq!(control.bounds().1, (800, 600));
+        assert!(eval.try_take().is_none());
+
+        control.complete_creation();
+        assert_eq!(
+            control.navigations(),
+            ["https://example.com/", "https://example.com/two"]
+        );
+        assert_eq!(control.zoom(), 2.0);
+        assert_eq!(control.bounds().1, (400, 300));
+        assert_eq!(control.pending_scripts(), ["1 + 2"]);
+        assert!(control.resolve_script(3));
+        assert_eq!(eval.try_take().unwrap().unwrap(), 3);
+    }
+
+    #[test]
+    fn zoom_changed_waits_for_the_zoom_to_be_applied() {
+        let control = control();
+        let zooms = Rc::new(RefCell::new(Vec::new()));
+        let zooms2 = zooms.clone();
+        control
+            .add_zoom_changed(move |zoom| zooms2.borrow_mut().push(zoom))
+            .unwrap()
+            .detach();
+        control.set_zoom(2.0).unwrap();
+        assert!(zooms.borrow().is_empty());
+
+        control.complete_creation();
+        assert_eq!(*zooms.borrow(), [2.0]);
+        control.set_zoom(0.5).unwrap();
+        assert_eq!(*zooms.borrow(), [2.0, 0.5]);
+    }
+
+    #[test]
+    fn handlers_added_before_creation_see_queued_navigations() {
+        let control = control();
+        let started = Rc::new(RefCell::new(Vec::new()));
+        let started2 = started.clone();
+        control
+            .add_navigation_starting(move |event| started2.borrow_mut().push(event.url.clone()))
+            .unwrap()
+            .detach();
+        control.navigate("https://example.com/").unwrap();
+        assert!(started.borrow().is_empty());
+        control.complete_creation();
+        assert_eq!(*started.borrow(), ["https://example.com/"]);
+    }
+
+    #[test]
+    fn fail_creation_fails_queued_and_later_operations() {
+        let control = control();
+        control.navigate("https://example.com/").unwrap();
+        let mut eval = control.eval::<i32>("1 + 2");
+        control.fail_creation(Error::BackendUnavailable("testing".into()));
+        match eval.try_take() {
+            Some(Err(Error::CreationFailed)) => (),
+            other => panic!("unexpected eval result {:?}", other),
+        }
+        match control.navigate("https://example.com/") {
+            Err(Error::CreationFailed) => (),
+            other => panic!("unexpected navigate result {:?}", other),
+        }
+        match control.eval::<i32>("1").try_take() {
+            Some(Err(Error::CreationFailed)) => (),
+            other => panic!("unexpected eval result {:?}", other),
+        }
+        assert!(control.navigations().is_empty());
+        // Creation can’t complete after it has failed.
+        control.complete_creation();
+        assert!(!control.is_created());
+    }
+
+    #[test]
+    fn history() {
+        let control = control();
+        control.complete_creation();
+        assert!(!control.can_go_back());
+        control.navigate("https://example.com/one").unwrap();
+        control.navigate("https://example.com/two").unwrap();
+        control.inject_navigation("https://example.com/three");
+        assert_eq!(control.url().as_deref(), Some("https://example.com/three"));
+        assert!(control.can_go_back());
+        assert!(!control.can_go_forward());
+
+        control.go_back().unwrap();
+        control.go_back().unwrap();
+        assert_eq!(control.url().as_deref(), Some("https://example.com/one"));
+        assert!(!control.can_go_back());
+        control.go_back().unwrap();
+        assert_eq!(control.url().as_deref(), Some("https://example.com/one"));
+        control.go_forward().unwrap();
+        assert_eq!(control.url().as_deref(), Some("https://example.com/two"));
+
+        // Navigating discards the forward history.
+        control.navigate("https://example.com/four").unwrap();
+        assert!(!control.can_go_forward());
+        control.go_back().unwrap();
+        assert_eq!(control.url().as_deref(), Some("https://example.com/two"));
+
+        control.go_forward().unwrap();
+        control.inject_redirect("https://example.com/five");
+        assert_eq!(control.url().as_deref(), Some("https://example.com/five"));
+        control.go_back().unwrap();
+        assert_eq!(control.url().as_deref(), Some("https://example.com/two"));
+    }
+
+    #[test]
+    fn cancelled_navigations_stay_out_of_history() {
+        let control = control();
+        control.complete_creation();
+        control
+            .add_navigation_starting(|event| {
+                if event.url.contains("blocked") {
+                    event.cancel();
+                }
+            })
+            .unwrap()
+            .detach();
+        control.navigate("https://example.com/").unwrap();
+        control.navigate("https://example.com/blocked").unwrap();
+        control.inject_navigation("https://blocked.example.com/");
+        assert_eq!(control.url().as_deref(), Some("https://example.com/"));
+        assert!(!control.can_go_back());
+    }
+
+    #[test]
+    fn navigation_events_are_raised_in_order() {
+        let control = control();
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let log = |events: &Rc<RefCell<Vec<String>>>, name: &'static str| {
+            let events = events.clone();
+            move |url: &str| events.borrow_mut().push(format!("{} {}", name, url))
+        };
+        let starting = log(&events, "starting");
+        let loading = log(&events, "loading");
+        let loaded = log(&events, "loaded");
+        let completed = log(&events, "completed");
+        let subscriptions = vec![
+            control
+                .add_navigation_starting(move |event| starting(&event.url))
+                .unwrap(),
+            control
+                .add_content_loading(move |event| loading(&event.url))
+                .unwrap(),
+            control
+                .add_dom_content_loaded(move |event| loaded(&event.url))
+                .unwrap(),
+            control
+                .add_navigation_completed(move |event| completed(&event.url))
+                .unwrap(),
+        ];
+        control.complete_creation();
+        control.navigate("https://example.com/").unwrap();
+        control.finish_navigation(200);
+        control.inject_navigation("https://example.com/missing");
+        control.finish_navigation(404);
+        assert_eq!(
+            *events.borrow(),
+            [
+                "starting https://example.com/",
+                "loading https://example.com/",
+                "loaded https://example.com/",
+                "completed https://example.com/",
+                "starting https://example.com/missing",
+                "completed https://example.com/missing",
+            ]
+        );
+
+        // Once the subscriptions are dropped, the handlers are gone.
+        drop(subscriptions);
+        control.navigate("https://example.com/again").unwrap();
+        control.finish_navigation(200);
+        assert_eq!(events.borrow().len(), 6);
+    }
+
+    #[test]
+    fn new_window_requests() {
+        let popup = control();
+        popup.complete_creation();
+        let control = control();
+        control.complete_creation();
+        let mut unused_popup = Some(WebViewControl::Mock(popup.clone()));
+        control
+            .add_new_window_requested(move |request| {
+                assert_eq!(request.referrer.as_deref(), Some("https://example.com/"));
+                if request.url.ends_with("/here") {
+                    request.navigate_here();
+                } else if request.url.ends_with("/popup") {
+                    request.open_in(unused_popup.take().unwrap());
+                }
+                // Only the first choice counts.
+                request.ignore();
+            })
+            .unwrap()
+            .detach();
+        let referrer = Some("https://example.com/");
+
+        assert!(control
+            .inject_new_window_request("https://example.com/ad", referrer)
+            .is_none());
+        assert_eq!(control.url(), None);
+
+        assert!(control
+            .inject_new_window_request("https://example.com/here", referrer)
+            .is_none());
+        assert_eq!(control.url().as_deref(), Some("https://example.com/here"));
+
+        let opened = control
+            .inject_new_window_request("https://example.com/popup", referrer)
+            .unwrap();
+        assert_eq!(opened.navigations(), ["https://example.com/popup"]);
+        assert_eq!(popup.url().as_deref(), Some("https://example.com/popup"));
+        assert_eq!(control.url().as_deref(), Some("https://example.com/here"));
+    }
+}
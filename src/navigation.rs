@@ -0,0 +1,512 @@
+//! The events of a navigation, from starting to completing.
+//!
+//! For each navigation of the top-level document, the control raises, in order:
+//!
+//! 1. `NavigationStarting`, once for the navigation and then once for each redirect, any of which
+//!    may cancel the navigation;
+//! 2. `ContentLoading`, once the new document has started to arrive;
+//! 3. `DomContentLoaded`, once it has been parsed;
+//! 4. `NavigationCompleted`, whether it succeeded or not.
+//!
+//! A navigation that fails doesn’t get to the intermediate events. Whether a navigation cancelled
+//! from `NavigationStarting` still gets a `NavigationCompleted` (with `WebErrorKind::Cancelled`)
+//! depends on the engine.
+//!
+//! A `NavigationPolicy` uses `NavigationStarting` to restrict where a control can go.
+//!
+//! Pages opening new windows (with `window.open` or links with `target="_blank"`) don’t navigate
+//! the control at all, but raise `NewWindowRequested`, whose handler decides where the page goes.
+
+use std::borrow::Cow;
+use std::cell::{Cell, RefCell};
+use std::fmt;
+
+use crate::error::Error;
+use crate::scheme;
+use crate::subscription::Subscription;
+use crate::{WebView, WebViewControl};
+
+/// A navigation is about to start, or has been redirected.
+#[derive(Debug)]
+pub struct NavigationStarting {
+    /// The URL being navigated to.
+    pub url: String,
+    /// Whether this is a redirect of a navigation that has already started, or `None` where the
+    /// engine doesn’t say, as with EdgeHTML.
+    pub is_redirect: Option<bool>,
+    cancelled: Cell<bool>,
+}
+
+impl NavigationStarting {
+    #[cfg_attr(not(any_backend), allow(dead_code))]
+    pub(crate) fn new(url: String, is_redirect: Option<bool>) -> NavigationStarting {
+        NavigationStarting {
+            url,
+            is_redirect,
+            cancelled: Cell::new(false),
+        }
+    }
+
+    /// Cancel the navigation, leaving the current document in place.
+    pub fn cancel(&self) {
+        self.cancelled.set(true);
+    }
+
+    /// Whether a handler has cancelled the navigation.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.get()
+    }
+}
+
+/// The new document has started to arrive.
+#[derive(Debug, Clone)]
+pub struct ContentLoading {
+    pub url: String,
+}
+
+/// The new document has been parsed, as with the page’s own `DOMContentLoaded` event.
+#[derive(Debug, Clone)]
+pub struct DomContentLoaded {
+    pub url: String,
+}
+
+/// A navigation has finished, successfully or not.
+#[derive(Debug, Clone)]
+pub struct NavigationCompleted {
+    pub url: String,
+    /// False if the navigation failed, including if the server responded with an HTTP error
+    /// status (400 or above).
+    pub is_success: bool,
+    /// The HTTP status, where known. WebKitGTK reports it for every HTTP response; EdgeHTML only
+    /// for error statuses.
+    pub http_status: Option<u16>,
+    /// Why the navigation failed, if it did.
+    pub error: Option<WebErrorKind>,
+}
+
+impl NavigationCompleted {
+    /// The outcome as a `Result`, with `Error::Navigation` if the navigation failed.
+    pub fn result(&self) -> Result<(), Error> {
+        if self.is_success {
+            return Ok(());
+        }
+        Err(Error::Navigation {
+            url: self.url.clone(),
+            kind: self
+                .error
+                .clone()
+                .unwrap_or_else(|| WebErrorKind::Other("unknown error".to_owned())),
+            http_status: self.http_status,
+        })
+    }
+}
+
+/// Why a navigation failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WebErrorKind {
+    /// The navigation was cancelled, e.g. by `stop` or by another navigation.
+    Cancelled,
+    /// The host name could not be resolved.
+    HostNotFound,
+    /// The server could not be reached.
+    CannotConnect,
+    /// The connection was lost part way through.
+    ConnectionLost,
+    /// The server took too long to respond.
+    Timeout,
+    /// The server’s certificate was not acceptable.
+    Certificate,
+    /// The server responded with an HTTP error status, found in `http_status`.
+    Http,
+    /// Something else, as the engine describes it.
+    Other(String),
+}
+
+/// A page asked to open a URL in a new window.
+///
+/// A handler chooses what happens by calling one of `ignore`, `navigate_here` or `open_in`, of
+/// which only the first call counts. If no handler chooses, the engine does whatever it does by
+/// default, which for WebKitGTK is to ignore the request.
+#[derive(Debug)]
+pub struct NewWindowRequested {
+    /// The URL to open.
+    pub url: String,
+    /// The URL of the page that asked, where the engine reports it.
+    pub referrer: Option<String>,
+    action: RefCell<Option<NewWindowAction>>,
+}
+
+/// What to do with a `NewWindowRequested`.
+pub(crate) enum NewWindowAction {
+    Ignore,
+    NavigateHere,
+    Open(WebViewControl),
+}
+
+impl fmt::Debug for NewWindowAction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            NewWindowAction::Ignore => "Ignore",
+            NewWindowAction::NavigateHere => "NavigateHere",
+            NewWindowAction::Open(_) => "Open(..)",
+        })
+    }
+}
+
+#[cfg_attr(not(any_backend), allow(dead_code))]
+impl NewWindowRequested {
+    pub(crate) fn new(url: String, referrer: Option<String>) -> NewWindowRequested {
+        NewWindowRequested {
+            url,
+            referrer,
+            action: RefCell::new(None),
+        }
+    }
+
+    /// Do nothing with the request.
+    pub fn ignore(&self) {
+        self.choose(NewWindowAction::Ignore);
+    }
+
+    /// Navigate this control to the URL, instead of opening a new window.
+    pub fn navigate_here(&self) {
+        self.choose(NewWindowAction::NavigateHere);
+    }
+
+    /// Open the URL in a new control, which the application has created in a window of its own.
+    ///
+    /// The new control mustn’t have been navigated anywhere yet. Where the engine supports it,
+    /// the new page keeps its `window.opener` relationship; for that, on EdgeHTML, the control
+    /// must have been created by the same `edge::Process` as this one, and on WebKitGTK, by
+    /// `gtk::Process::create_related_control`. Otherwise, the control is simply navigated to the
+    /// URL.
+    pub fn open_in(&self, control: WebViewControl) {
+        self.choose(NewWindowAction::Open(control));
+    }
+
+    /// Whether a handler has chosen what to do with the request.
+    pub fn is_handled(&self) -> bool {
+        self.action.borrow().is_some()
+    }
+
+    fn choose(&self, action: NewWindowAction) {
+        let mut chosen = self.action.borrow_mut();
+        if chosen.is_none() {
+            *chosen = Some(action);
+        }
+    }
+
+    /// What a handler chose to do, if anything.
+    pub(crate) fn take_action(&self) -> Option<NewWindowAction> {
+        self.action.borrow_mut().take()
+    }
+}
+
+/// What a `NavigationPolicy` does with a navigation to a URL it doesn’t allow.
+pub enum Disallowed {
+    /// Cancel the navigation.
+    Block,
+    /// Cancel the navigation, and pass the URL to the function, which would typically open it in
+    /// the system’s browser.
+    OpenExternally(Box<dyn FnMut(&str)>),
+    /// Ask the function whether to allow the navigation anyway.
+    Ask(Box<dyn FnMut(&str) -> bool>),
+}
+
+/// Which URLs a control may navigate to, and what happens to the rest.
+///
+/// ```rust,ignore
+/// let policy = NavigationPolicy::new()
+///     .allow("https://example.com")
+///     .allow("https://*.example.com/*")
+///     .disallowed(Disallowed::OpenExternally(Box::new(|url| open_in_browser(url))));
+/// let _subscription = policy.attach(&control)?;
+/// ```
+///
+/// The policy applies to every navigation of the top-level document, including those made with
+/// `WebView::navigate`, so it must allow the application’s own content too. (On EdgeHTML, pages
+/// served with `WebView::register_scheme` have `ms-local-stream:` URLs; see the `scheme` module.)
+/// `about:blank`, which documents loaded with `WebView::navigate_to_string` have, is always
+/// allowed.
+pub struct NavigationPolicy {
+    allowed: Vec<String>,
+    disallowed: Disallowed,
+    recheck_redirects: bool,
+}
+
+impl NavigationPolicy {
+    /// A policy that allows nothing but `about:blank`, blocks everything else, and rechecks
+    /// redirects.
+    pub fn new() -> NavigationPolicy {
+        NavigationPolicy {
+            allowed: Vec::new(),
+            disallowed: Disallowed::Block,
+            recheck_redirects: true,
+        }
+    }
+
+    /// Allow URLs matching the pattern.
+    ///
+    /// A pattern containing `*` is a glob, with `*` matching any run of characters (including
+    /// none). Its scheme, host and the rest (path, query and fragment) are each matched against
+    /// the same part of the URL, so `https://*.example.com/*` allows any page on any subdomain of
+    /// example.com but nothing else; with no path, only the root is allowed. Anything else is an
+    /// origin, like `https://example.com` or `http://localhost:8080`, which allows every URL with
+    /// that origin.
+    ///
+    /// Hosts and schemes are compared ignoring case, default ports (e.g. `:443` for https) are
+    /// ignored, and user names and passwords in URLs are not part of the host.
+    pub fn allow(mut self, pattern: &str) -> NavigationPolicy {
+        self.allowed.push(pattern.to_owned());
+        self
+    }
+
+    /// Set what happens to navigations that aren’t allowed. The default is `Disallowed::Block`.
+    pub fn disallowed(mut self, disallowed: Disallowed) -> NavigationPolicy {
+        self.disallowed = disallowed;
+        self
+    }
+
+    /// Set whether redirects are checked like any other navigation (the default), rather than
+    /// always being allowed. EdgeHTML doesn’t say which navigations are redirects, so there they
+    /// are always checked.
+    pub fn recheck_redirects(mut self, recheck_redirects: bool) -> NavigationPolicy {
+        self.recheck_redirects = recheck_redirects;
+        self
+    }
+
+    /// Whether the policy allows the URL, without considering what happens if it doesn’t.
+    pub fn allows(&self, url: &str) -> bool {
+        if url.is_empty() || url == "about:blank" {
+            return true;
+        }
+        let parts = match url_parts(url) {
+            Some(parts) => parts,
+            None => return false,
+        };
+        self.allowed.iter().any(|pattern| {
+            if pattern.contains('*') {
+                pattern_matches(pattern, &parts)
+            } else {
+                origin_of(pattern)
+                    .is_some_and(|origin| origin == format!("{}://{}", parts.scheme, parts.host))
+            }
+        })
+    }
+
+    /// Apply the policy to the control’s navigations, until the returned subscription is dropped.
+    pub fn attach<C: WebView>(mut self, control: &C) -> Result<Subscription, Error> {
+        control.add_navigation_starting(move |event| {
+            if event.is_cancelled() || (event.is_redirect == Some(true) && !self.recheck_redirects)
+            {
+                return;
+            }
+            if self.allows(&event.url) {
+                return;
+            }
+            match self.disallowed {
+                Disallowed::Block => event.cancel(),
+                Disallowed::OpenExternally(ref mut open) => {
+                    event.cancel();
+                    open(&event.url);
+                }
+                Disallowed::Ask(ref mut ask) => {
+                    if !ask(&event.url) {
+                        event.cancel();
+                    }
+                }
+            }
+        })
+    }
+}
+
+impl Default for NavigationPolicy {
+    fn default() -> NavigationPolicy {
+        NavigationPolicy::new()
+    }
+}
+
+/// A URL split up the way browsers do it, as far as `NavigationPolicy` needs.
+struct UrlParts<'a> {
+    /// Lowercase.
+    scheme: String,
+    /// Lowercase, with any port but the scheme’s default, and without any user name or password.
+    host: String,
+    /// The path, query and fragment.
+    rest: Cow<'a, str>,
+}
+
+/// Schemes for which browsers treat `\` like `/`.
+fn is_special(scheme: &str) -> bool {
+    matches!(scheme, "http" | "https" | "ws" | "wss" | "ftp" | "file")
+}
+
+fn url_parts(url: &str) -> Option<UrlParts<'_>> {
+    let (scheme, rest) = scheme::split_url(url)?;
+    let scheme = scheme.to_ascii_lowercase();
+    let special = is_special(&scheme);
+    let end = rest
+        .find(|c| matches!(c, '/' | '?' | '#') || (special && c == '\\'))
+        .unwrap_or(rest.len());
+    let authority = &rest[..end];
+    // Any user name and password aren’t part of the host.
+    let host = match authority.rfind('@') {
+        Some(at) => &authority[at + 1..],
+        None => authority,
+    };
+    let host = without_default_port(&scheme, &host.to_ascii_lowercase());
+    let rest = &rest[end..];
+    let rest = if special {
+        Cow::Owned(rest.replace('\\', "/"))
+    } else {
+        Cow::Borrowed(rest)
+    };
+    Some(UrlParts { scheme, host, rest })
+}
+
+/// Leave out the scheme’s default port, as URLs and patterns may or may not have it.
+fn without_default_port(scheme: &str, host: &str) -> String {
+    let default_port = match scheme {
+        "http" | "ws" => ":80",
+        "https" | "wss" => ":443",
+        _ => "",
+    };
+    host.strip_suffix(default_port).unwrap_or(host).to_owned()
+}
+
+/// The origin of a URL, e.g. `https://example.com:8443`, if it has one.
+pub(crate) fn origin_of(url: &str) -> Option<String> {
+    let parts = url_parts(url)?;
+    Some(format!("{}://{}", parts.scheme, parts.host))
+}
+
+/// Match a glob pattern from `NavigationPolicy::allow` against a URL.
+///
+/// The pattern is split up like a URL, and each part matched only against the same part of the
+/// URL, so that a `*` in the host can’t reach into the path or a user name.
+fn pattern_matches(pattern: &str, url: &UrlParts) -> bool {
+    let (scheme, rest) = match pattern.find("://") {
+        Some(colon) => (&pattern[..colon], &pattern[colon + 3..]),
+        None => return false,
+    };
+    let scheme = scheme.to_ascii_lowercase();
+    let end = rest.find(['/', '?', '#', '\\']).unwrap_or(rest.len());
+    let host = without_default_port(&scheme, &rest[..end].to_ascii_lowercase());
+    let path = &rest[end..];
+    glob_matches(&scheme, &url.scheme)
+        && glob_matches(&host, &url.host)
+        && if path.is_empty() {
+            url.rest.is_empty() || url.rest == "/"
+        } else {
+            glob_matches(path, &url.rest)
+        }
+}
+
+/// Match a glob where `*` matches any run of characters.
+fn glob_matches(pattern: &str, text: &str) -> bool {
+    let mut parts = pattern.split('*');
+    // There is always a first part, even if it’s empty.
+    let first = parts.next().unwrap_or("");
+    let mut rest = match text.strip_prefix(first) {
+        Some(rest) => rest,
+        None => return false,
+    };
+    let parts: Vec<&str> = parts.collect();
+    for (i, part) in parts.iter().enumerate() {
+        if i == parts.len() - 1 {
+            // The last part must match the end, after a `*` that can take up the slack.
+            return rest.ends_with(part);
+        }
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    // No `*` at all, so the whole text had to be the first part.
+    rest.is_empty()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(patterns: &[&str]) -> NavigationPolicy {
+        let mut policy = NavigationPolicy::new();
+        for pattern in patterns {
+            policy = policy.allow(pattern);
+        }
+        policy
+    }
+
+    #[test]
+    fn origin() {
+        assert_eq!(
+            origin_of("HTTPS://Example.com/a").as_deref(),
+            Some("https://example.com")
+        );
+        assert_eq!(
+            origin_of("https://example.com:443/").as_deref(),
+            Some("https://example.com")
+        );
+        assert_eq!(
+            origin_of("http://example.com:8080").as_deref(),
+            Some("http://example.com:8080")
+        );
+        assert_eq!(
+            origin_of("https://user:pw@example.com/").as_deref(),
+            Some("https://example.com")
+        );
+        assert_eq!(
+            origin_of("https://evil.com\\@example.com").as_deref(),
+            Some("https://evil.com")
+        );
+        assert_eq!(origin_of("not a url"), None);
+    }
+
+    #[test]
+    fn origin_patterns() {
+        let policy = policy(&["https://example.com:443", "http://localhost:8080/"]);
+        assert!(policy.allows("https://example.com/page?q#f"));
+        assert!(policy.allows("https://EXAMPLE.com:443"));
+        assert!(policy.allows("http://localhost:8080/x"));
+        assert!(policy.allows("about:blank"));
+        assert!(!policy.allows("http://example.com/"));
+        assert!(!policy.allows("http://localhost/"));
+        assert!(!policy.allows("https://example.com.evil.com/"));
+        assert!(!policy.allows("https://evil.com\\@example.com"));
+        assert!(!policy.allows("https://evil.com\\@example.com/"));
+    }
+
+    #[test]
+    fn glob_patterns() {
+        let policy = policy(&["https://*.example.com/*"]);
+        assert!(policy.allows("https://www.example.com/"));
+        assert!(policy.allows("https://a.b.example.com/x/y?z"));
+        assert!(policy.allows("https://user@www.example.com/"));
+        assert!(!policy.allows("https://example.com/"));
+        assert!(!policy.allows("https://attacker.com/.example.com/x"));
+        assert!(!policy.allows("https://attacker.com?.example.com/x"));
+        assert!(!policy.allows("https://www.example.com@attacker.com/"));
+        assert!(!policy.allows("https://attacker.com\\.example.com/x"));
+        assert!(!policy.allows("http://www.example.com/"));
+    }
+
+    #[test]
+    fn glob_patterns_without_path() {
+        let policy = policy(&["https://*.example.com"]);
+        assert!(policy.allows("https://www.example.com"));
+        assert!(policy.allows("https://www.example.com/"));
+        assert!(!policy.allows("https://www.example.com/x"));
+        assert!(!policy.allows("https://evil.com#.example.com"));
+        assert!(!policy.allows("https://evil.com/#.example.com"));
+    }
+
+    #[test]
+    fn glob_patterns_with_default_port() {
+        let policy = policy(&["https://*.example.com:443/docs/*"]);
+        assert!(policy.allows("https://www.example.com/docs/a"));
+        assert!(policy.allows("https://www.example.com:443/docs/a"));
+        assert!(!policy.allows("https://www.example.com:8443/docs/a"));
+        assert!(!policy.allows("https://www.example.com/other"));
+    }
+}
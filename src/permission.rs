@@ -0,0 +1,293 @@
+//! Requests from pages for permission to use things like the user’s location or camera.
+//!
+//! A handler added with `WebView::add_permission_requested` can answer a request there and then
+//! with `allow()` or `deny()`, or `defer()` it to answer later, e.g. after asking the user:
+//!
+//! ```rust,ignore
+//! control.add_permission_requested(|request| match request.kind {
+//!     PermissionKind::Notifications => request.allow(),
+//!     PermissionKind::Geolocation => {
+//!         let deferred = request.defer();
+//!         spawn_local(async move {
+//!             if ask("Share your location?").await {
+//!                 deferred.allow();
+//!             } else {
+//!                 deferred.deny();
+//!             }
+//!         });
+//!     }
+//!     _ => request.deny(),
+//! })?.detach();
+//! ```
+//!
+//! A request that no handler answers is left to the engine, which generally denies it.
+
+use std::cell::{Cell, RefCell};
+use std::fmt;
+
+/// Answers a deferred request: true to allow, false to deny.
+type Respond = Box<dyn FnOnce(bool)>;
+
+/// Defers the request in the engine, returning how to answer it later.
+type Defer = Box<dyn FnOnce() -> Respond>;
+
+/// What a page is asking permission to use.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PermissionKind {
+    /// The user’s location.
+    Geolocation,
+    /// A camera or microphone.
+    Media,
+    /// The contents of the screen.
+    ScreenCapture,
+    /// Showing notifications.
+    Notifications,
+    /// Locking the pointer, as games do.
+    PointerLock,
+    /// Storing more data than usual, or data accessible to other sites.
+    Storage,
+    /// Something else, as the engine describes it.
+    Other(String),
+}
+
+/// Whether a permission request has been answered, and how.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PermissionState {
+    Unanswered,
+    Allowed,
+    Denied,
+    /// To be answered later, through the `DeferredPermission`.
+    Deferred,
+}
+
+/// A request from a page for permission to use something.
+///
+/// Only the first answer counts; later ones are ignored.
+pub struct PermissionRequest {
+    pub kind: PermissionKind,
+    /// The origin of the page making the request, e.g. `https://example.com`.
+    pub origin: String,
+    state: Cell<PermissionState>,
+    defer: RefCell<Option<Defer>>,
+}
+
+impl PermissionRequest {
+    /// `defer` is called when a handler defers the request, before the handler gets the
+    /// `DeferredPermission`, so that the engine knows to wait before the answer can arrive; it
+    /// returns what answers the request. The backend applies any other answer itself once the
+    /// handlers have run, according to `state()`.
+    #[cfg_attr(not(any_backend), allow(dead_code))]
+    pub(crate) fn new<D, R>(kind: PermissionKind, origin: String, defer: D) -> PermissionRequest
+    where
+        D: FnOnce() -> R + 'static,
+        R: FnOnce(bool) + 'static,
+    {
+        PermissionRequest {
+            kind,
+            origin,
+            state: Cell::new(PermissionState::Unanswered),
+            defer: RefCell::new(Some(Box::new(move || Box::new(defer()) as Respond))),
+        }
+    }
+
+    /// Grant the permission.
+    pub fn allow(&self) {
+        self.answer(PermissionState::Allowed);
+    }
+
+    /// Refuse the permission.
+    pub fn deny(&self) {
+        self.answer(PermissionState::Denied);
+    }
+
+    /// Put off answering the request until the returned `DeferredPermission` is used.
+    ///
+    /// If the request has already been answered, the `DeferredPermission` does nothing.
+    #[must_use = "dropping a DeferredPermission denies the permission"]
+    pub fn defer(&self) -> DeferredPermission {
+        let respond = if self.answer(PermissionState::Deferred) {
+            self.defer.borrow_mut().take().map(|defer| defer())
+        } else {
+            None
+        };
+        DeferredPermission {
+            kind: self.kind.clone(),
+            origin: self.origin.clone(),
+            respond,
+        }
+    }
+
+    /// Whether a handler has answered the request, and how.
+    pub fn state(&self) -> PermissionState {
+        self.state.get()
+    }
+
+    /// Returns false if the request had already been answered.
+    fn answer(&self, state: PermissionState) -> bool {
+        if self.state.get() == PermissionState::Unanswered {
+            self.state.set(state);
+            true
+        } else {
+            false
+        }
+    }
+}
+
+impl fmt::Debug for PermissionRequest {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("PermissionRequest")
+            .field("kind", &self.kind)
+            .field("origin", &self.origin)
+            .field("state", &self.state.get())
+            .finish()
+    }
+}
+
+/// A permission request that was deferred, to be answered later.
+///
+/// It must be answered on the thread the control belongs to. Dropping it without answering denies
+/// the permission.
+pub struct DeferredPermission {
+    pub kind: PermissionKind,
+    pub origin: String,
+    respond: Option<Respond>,
+}
+
+impl DeferredPermission {
+    /// Grant the permission.
+    pub fn allow(mut self) {
+        self.respond(true);
+    }
+
+    /// Refuse the permission.
+    pub fn deny(mut self) {
+        self.respond(false);
+    }
+
+    fn respond(&mut self, allow: bool) {
+        if let Some(respond) = self.respond.take() {
+            respond(allow);
+        }
+    }
+}
+
+impl Drop for DeferredPermission {
+    fn drop(&mut self) {
+        self.respond(false);
+    }
+}
+
+impl fmt::Debug for DeferredPermission {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("DeferredPermission")
+            .field("kind", &self.kind)
+            .field("origin", &self.origin)
+            .field("answered", &self.respond.is_none())
+            .finish()
+    }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use std::rc::Rc;
+
+    use super::*;
+    use crate::mock::{Control, Process};
+    use crate::settings::ControlSettings;
+    use crate::WebView;
+
+    fn control_answering<F>(handler: F) -> Control
+    where
+        F: FnMut(&PermissionRequest) + 'static,
+    {
+        let control = Process::new()
+            .create_control((0, 0), (800, 600), &ControlSettings::default())
+            .unwrap();
+        control.complete_creation();
+        control.add_permission_requested(handler).unwrap().detach();
+        control
+    }
+
+    #[test]
+    fn allowing_and_denying() {
+        let control = control_answering(|request| match request.kind {
+            PermissionKind::Notifications => request.allow(),
+            _ => request.deny(),
+        });
+        control.inject_permission_request(PermissionKind::Notifications, "https://example.com/");
+        control.inject_permission_request(PermissionKind::Geolocation, "https://example.com/");
+        assert_eq!(
+            control.permission_answers(),
+            [PermissionState::Allowed, PermissionState::Denied]
+        );
+    }
+
+    #[test]
+    fn only_the_first_answer_counts() {
+        let control = control_answering(|request| {
+            request.allow();
+            request.deny();
+            let _ = request.defer();
+            assert_eq!(request.state(), PermissionState::Allowed);
+        });
+        control.inject_permission_request(PermissionKind::Media, "https://example.com/");
+        assert_eq!(control.permission_answers(), [PermissionState::Allowed]);
+    }
+
+    #[test]
+    fn requests_with_no_answer_are_left_to_the_engine() {
+        let control = control_answering(|request| {
+            assert_eq!(request.origin, "https://example.com");
+        });
+        control.inject_permission_request(PermissionKind::Storage, "https://example.com/page");
+        assert_eq!(control.permission_answers(), [PermissionState::Unanswered]);
+    }
+
+    #[test]
+    fn deferring() {
+        let deferred = Rc::new(RefCell::new(Vec::new()));
+        let deferred2 = deferred.clone();
+        let control = control_answering(move |request| {
+            deferred2.borrow_mut().push(request.defer());
+        });
+        control.inject_permission_request(PermissionKind::Geolocation, "https://example.com/");
+        control.inject_permission_request(PermissionKind::Media, "https://example.com/");
+        assert_eq!(
+            control.permission_answers(),
+            [PermissionState::Deferred, PermissionState::Deferred]
+        );
+
+        let media = deferred.borrow_mut().pop().unwrap();
+        assert_eq!(media.kind, PermissionKind::Media);
+        media.deny();
+        deferred.borrow_mut().pop().unwrap().allow();
+        assert_eq!(
+            control.permission_answers(),
+            [PermissionState::Allowed, PermissionState::Denied]
+        );
+    }
+
+    #[test]
+    fn dropping_a_deferred_request_denies_it() {
+        let deferred = Rc::new(RefCell::new(None));
+        let deferred2 = deferred.clone();
+        let control = control_answering(move |request| {
+            let permission = request.defer();
+            if request.kind == PermissionKind::Geolocation {
+                *deferred2.borrow_mut() = Some(permission);
+            }
+        });
+        control.inject_permission_request(PermissionKind::Notifications, "https://example.com/");
+        control.inject_permission_request(PermissionKind::Geolocation, "https://example.com/");
+        assert_eq!(
+            control.permission_answers(),
+            [PermissionState::Denied, PermissionState::Deferred]
+        );
+
+        deferred.borrow_mut().take();
+        assert_eq!(
+            control.permission_answers(),
+            [PermissionState::Denied, PermissionState::Denied]
+        );
+    }
+}
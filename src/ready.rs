@@ -0,0 +1,73 @@
+//! Tracking of asynchronous control creation, shared by the backends’ `Ready` futures.
+
+use std::task::{Context, Poll, Waker};
+
+use crate::error::Error;
+
+enum State {
+    Pending,
+    // The errors are handed out to the first future to see them; the rest get something vaguer.
+    Created { error: Option<Error> },
+    Failed { error: Option<Error> },
+}
+
+pub(crate) struct Readiness {
+    state: State,
+    wakers: Vec<Waker>,
+}
+
+impl Readiness {
+    /// Readiness for a control still being created.
+    pub(crate) fn pending() -> Readiness {
+        Readiness {
+            state: State::Pending,
+            wakers: Vec::new(),
+        }
+    }
+
+    // EdgeHTML checks for the control itself instead.
+    #[cfg(feature = "mock")]
+    pub(crate) fn is_created(&self) -> bool {
+        matches!(self.state, State::Created { .. })
+    }
+
+    pub(crate) fn has_failed(&self) -> bool {
+        matches!(self.state, State::Failed { .. })
+    }
+
+    /// Record that the control has been created, waking anything waiting for it.
+    ///
+    /// The error, if any, is from replaying operations queued during creation: the control is
+    /// usable, but the caller should still hear about it.
+    pub(crate) fn set_created(&mut self, error: Option<Error>) {
+        self.state = State::Created { error };
+        self.wake();
+    }
+
+    /// Record that the control could not be created, waking anything waiting for it.
+    pub(crate) fn set_failed(&mut self, error: Error) {
+        self.state = State::Failed { error: Some(error) };
+        self.wake();
+    }
+
+    fn wake(&mut self) {
+        for waker in self.wakers.drain(..) {
+            waker.wake();
+        }
+    }
+
+    pub(crate) fn poll(&mut self, cx: &mut Context) -> Poll<Result<(), Error>> {
+        match self.state {
+            State::Pending => {
+                if !self.wakers.iter().any(|waker| waker.will_wake(cx.waker())) {
+                    self.wakers.push(cx.waker().clone());
+                }
+                Poll::Pending
+            }
+            State::Created { ref mut error } => Poll::Ready(error.take().map_or(Ok(()), Err)),
+            State::Failed { ref mut error } => {
+                Poll::Ready(Err(error.take().unwrap_or(Error::CreationFailed)))
+            }
+        }
+    }
+}
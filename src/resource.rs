@@ -0,0 +1,233 @@
+//! Intercepting the requests a page makes for its resources (images, scripts, `fetch` calls and
+//! the like), e.g. to stub a backend API in tests or to keep a page offline.
+//!
+//! ```rust,ignore
+//! control.add_web_resource_requested(|request| {
+//!     if request.url.starts_with("https://api.example.com/") {
+//!         request.respond(
+//!             SchemeResponse::ok(r#"{"items":[]}"#)
+//!                 .with_header("Content-Type", "application/json"),
+//!         );
+//!     } else if !request.url.starts_with("app://") {
+//!         request.block();
+//!     }
+//! })?.detach();
+//! ```
+//!
+//! A handler can let a request through unchanged (by doing nothing), let it through with changed
+//! headers, block it, or answer it itself without it ever reaching the network. Only the first
+//! choice to block or answer counts.
+//!
+//! Only EdgeHTML supports this. WebKitGTK offers no way of intercepting requests outside the web
+//! process, so `add_web_resource_requested` fails there.
+
+use std::cell::{Cell, RefCell};
+
+use crate::scheme::SchemeResponse;
+
+/// A page is about to request a resource.
+#[derive(Debug)]
+pub struct WebResourceRequest {
+    /// The URL of the resource.
+    pub url: String,
+    /// The HTTP method, e.g. `GET`.
+    pub method: String,
+    headers: RefCell<Vec<(String, String)>>,
+    headers_changed: Cell<bool>,
+    action: RefCell<Option<ResourceAction>>,
+}
+
+/// What to do with a `WebResourceRequest` instead of letting it through.
+#[derive(Debug)]
+#[cfg_attr(
+    not(any(all(windows, feature = "edgehtml"), feature = "mock")),
+    allow(dead_code)
+)]
+pub(crate) enum ResourceAction {
+    Block,
+    Respond(SchemeResponse),
+}
+
+// Only EdgeHTML and the mock backend can intercept requests.
+#[cfg_attr(
+    not(any(all(windows, feature = "edgehtml"), feature = "mock")),
+    allow(dead_code)
+)]
+impl WebResourceRequest {
+    pub(crate) fn new(
+        url: String,
+        method: String,
+        headers: Vec<(String, String)>,
+    ) -> WebResourceRequest {
+        WebResourceRequest {
+            url,
+            method,
+            headers: RefCell::new(headers),
+            headers_changed: Cell::new(false),
+            action: RefCell::new(None),
+        }
+    }
+
+    /// The request headers, in order, including any changes made by handlers.
+    pub fn headers(&self) -> Vec<(String, String)> {
+        self.headers.borrow().clone()
+    }
+
+    /// Get the value of a header, ignoring ASCII case in its name.
+    pub fn header(&self, name: &str) -> Option<String> {
+        self.headers
+            .borrow()
+            .iter()
+            .find(|(header_name, _)| header_name.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.clone())
+    }
+
+    /// Set a header, replacing any headers of that name.
+    pub fn set_header(&self, name: &str, value: &str) {
+        self.remove_header(name);
+        self.headers
+            .borrow_mut()
+            .push((name.to_owned(), value.to_owned()));
+        self.headers_changed.set(true);
+    }
+
+    /// Remove any headers of that name, ignoring ASCII case.
+    pub fn remove_header(&self, name: &str) {
+        self.headers
+            .borrow_mut()
+            .retain(|(header_name, _)| !header_name.eq_ignore_ascii_case(name));
+        self.headers_changed.set(true);
+    }
+
+    /// Stop the request from being made.
+    ///
+    /// On EdgeHTML, which can’t fail a request outright, the page gets an empty 403 Forbidden
+    /// response instead.
+    pub fn block(&self) {
+        self.choose(ResourceAction::Block);
+    }
+
+    /// Answer the request with the response, instead of making it.
+    pub fn respond(&self, response: SchemeResponse) {
+        self.choose(ResourceAction::Respond(response));
+    }
+
+    /// Whether a handler has blocked or answered the request.
+    pub fn is_handled(&self) -> bool {
+        self.action.borrow().is_some()
+    }
+
+    fn choose(&self, action: ResourceAction) {
+        let mut chosen = self.action.borrow_mut();
+        if chosen.is_none() {
+            *chosen = Some(action);
+        }
+    }
+
+    /// What a handler chose to do, if anything.
+    pub(crate) fn take_action(&self) -> Option<ResourceAction> {
+        self.action.borrow_mut().take()
+    }
+
+    /// The headers to send instead, if a handler changed them.
+    #[cfg_attr(not(all(windows, feature = "edgehtml")), allow(dead_code))]
+    pub(crate) fn changed_headers(&self) -> Option<Vec<(String, String)>> {
+        if self.headers_changed.get() {
+            Some(self.headers())
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use super::*;
+    use crate::mock::{Control, Process, ResourceOutcome};
+    use crate::settings::ControlSettings;
+    use crate::WebView;
+
+    fn control_intercepting<F>(handler: F) -> Control
+    where
+        F: FnMut(&WebResourceRequest) + 'static,
+    {
+        let control = Process::new()
+            .create_control((0, 0), (800, 600), &ControlSettings::default())
+            .unwrap();
+        control.complete_creation();
+        control
+            .add_web_resource_requested(handler)
+            .unwrap()
+            .detach();
+        control
+    }
+
+    #[test]
+    fn blocking() {
+        let control = control_intercepting(|request| {
+            if request.url.starts_with("https://tracker.example/") {
+                request.block();
+            }
+        });
+        match control.inject_resource_request("GET", "https://tracker.example/pixel.gif", &[]) {
+            ResourceOutcome::Blocked => {}
+            outcome => panic!("expected Blocked, got {:?}", outcome),
+        }
+        match control.inject_resource_request("GET", "https://example.com/", &[]) {
+            ResourceOutcome::Sent(headers) => assert!(headers.is_empty()),
+            outcome => panic!("expected Sent, got {:?}", outcome),
+        }
+    }
+
+    #[test]
+    fn responding() {
+        let control = control_intercepting(|request| {
+            assert!(!request.is_handled());
+            request.respond(
+                SchemeResponse::ok(r#"{"items":[]}"#)
+                    .with_header("Content-Type", "application/json"),
+            );
+            assert!(request.is_handled());
+            // Only the first choice counts.
+            request.block();
+        });
+        match control.inject_resource_request("POST", "https://api.example.com/items", &[]) {
+            ResourceOutcome::Responded(response) => {
+                assert_eq!(response.status, 200);
+                assert_eq!(
+                    response.headers,
+                    [("Content-Type".to_owned(), "application/json".to_owned())]
+                );
+            }
+            outcome => panic!("expected Responded, got {:?}", outcome),
+        }
+    }
+
+    #[test]
+    fn rewriting_headers() {
+        let control = control_intercepting(|request| {
+            assert_eq!(request.method, "GET");
+            assert_eq!(request.header("cookie").as_deref(), Some("a=1"));
+            request.remove_header("COOKIE");
+            request.set_header("Authorization", "Bearer token");
+            request.set_header("accept", "text/html");
+            assert!(!request.is_handled());
+        });
+        let outcome = control.inject_resource_request(
+            "GET",
+            "https://example.com/",
+            &[("Accept", "*/*"), ("Cookie", "a=1"), ("DNT", "1")],
+        );
+        match outcome {
+            ResourceOutcome::Sent(headers) => assert_eq!(
+                headers,
+                [
+                    ("DNT".to_owned(), "1".to_owned()),
+                    ("Authorization".to_owned(), "Bearer token".to_owned()),
+                    ("accept".to_owned(), "text/html".to_owned()),
+                ]
+            ),
+            outcome => panic!("expected Sent, got {:?}", outcome),
+        }
+    }
+}
@@ -0,0 +1,615 @@
+//! A JSON-RPC 2.0 bridge between Rust and the page, built on script notifications and scripts.
+//!
+//! The page calls Rust methods with a promise-based API:
+//!
+//! ```javascript
+//! rpc.call("add", [1, 2]).then(function (sum) { … }, function (error) { … });
+//! rpc.notify("log", "no response wanted");
+//! rpc.on("tick", function (params) { … });
+//! ```
+//!
+//! and Rust registers the methods with serde-typed handlers, and can send notifications the other
+//! way:
+//!
+//! ```rust,ignore
+//! let bridge = Bridge::new(control.clone())?;
+//! bridge.register("add", |(a, b): (i32, i32)| Ok(a + b));
+//! bridge.notify("tick", 1)?;
+//! ```
+//!
+//! Requests arrive through `window.external.notify`, so they share it with any other script
+//! notify handlers; messages that aren’t JSON-RPC requests are ignored. Responses and
+//! notifications are delivered by running a script.
+//!
+//! The shim providing `rpc` is added as an initialization script, so every page loaded after the
+//! bridge is created has it before its own scripts run, and is also run in the current page.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::{Rc, Weak};
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::{json, Value};
+
+use crate::error::Error;
+use crate::script;
+use crate::subscription::Subscription;
+use crate::WebView;
+
+/// The JavaScript that defines `rpc` in the page. It does nothing if `rpc` is already defined.
+pub const SHIM: &str = r#"(function () {
+    if (window.rpc && window.rpc._receive) {
+        return;
+    }
+    var nextId = 1;
+    var pending = {};
+    var listeners = {};
+    function send(message) {
+        message.jsonrpc = "2.0";
+        window.external.notify(JSON.stringify(message));
+    }
+    window.rpc = {
+        call: function (method, params) {
+            var id = nextId++;
+            return new Promise(function (resolve, reject) {
+                pending[id] = { resolve: resolve, reject: reject };
+                send({ id: id, method: method, params: params });
+            });
+        },
+        notify: function (method, params) {
+            send({ method: method, params: params });
+        },
+        on: function (method, listener) {
+            (listeners[method] = listeners[method] || []).push(listener);
+        },
+        off: function (method, listener) {
+            var list = listeners[method] || [];
+            var index = list.indexOf(listener);
+            if (index !== -1) {
+                list.splice(index, 1);
+            }
+        },
+        _receive: function (message) {
+            if (message.method !== undefined) {
+                (listeners[message.method] || []).slice().forEach(function (listener) {
+                    listener(message.params);
+                });
+                return;
+            }
+            var call = pending[message.id];
+            if (!call) {
+                return;
+            }
+            delete pending[message.id];
+            if (message.error) {
+                var error = new Error(message.error.message);
+                error.code = message.error.code;
+                error.data = message.error.data;
+                call.reject(error);
+            } else {
+                call.resolve(message.result);
+            }
+        }
+    };
+})();"#;
+
+/// A future to be run to completion on the UI thread. See `Bridge::set_spawner`.
+pub type LocalFuture = Pin<Box<dyn Future<Output = ()>>>;
+
+type MethodFuture = Pin<Box<dyn Future<Output = Result<Value, RpcError>>>>;
+
+enum MethodOutcome {
+    Ready(Result<Value, RpcError>),
+    Pending(MethodFuture),
+}
+
+type Method = Rc<dyn Fn(Value) -> MethodOutcome>;
+
+type Spawner = Rc<dyn Fn(LocalFuture)>;
+
+/// An error returned by a method, which the page sees as the promise rejecting with an `Error`
+/// that has `code` and `data` properties.
+///
+/// Any `std::error::Error` converts into one with the code `SERVER_ERROR`, so handlers can use `?`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<Value>,
+}
+
+impl RpcError {
+    /// The method does not exist.
+    pub const METHOD_NOT_FOUND: i64 = -32601;
+    /// The parameters could not be deserialised into what the method wanted.
+    pub const INVALID_PARAMS: i64 = -32602;
+    /// Something went wrong in the bridge itself.
+    pub const INTERNAL_ERROR: i64 = -32603;
+    /// The default for errors from methods; JSON-RPC reserves -32000 to -32099 for these.
+    pub const SERVER_ERROR: i64 = -32000;
+
+    pub fn new(code: i64, message: impl Into<String>) -> RpcError {
+        RpcError {
+            code,
+            message: message.into(),
+            data: None,
+        }
+    }
+
+    /// Attach additional information for the page, available as the error’s `data` property.
+    pub fn with_data(mut self, data: impl Serialize) -> RpcError {
+        self.data = serde_json::to_value(data).ok();
+        self
+    }
+}
+
+impl<E: std::error::Error> From<E> for RpcError {
+    fn from(error: E) -> RpcError {
+        RpcError::new(RpcError::SERVER_ERROR, error.to_string())
+    }
+}
+
+impl fmt::Display for RpcError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} (code {})", self.message, self.code)
+    }
+}
+
+#[derive(Deserialize)]
+struct Request {
+    jsonrpc: String,
+    // Absent for notifications, which get no response; but `"id": null` still gets one.
+    #[serde(default, deserialize_with = "deserialize_present")]
+    id: Option<Value>,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+struct BridgeInner<C> {
+    control: C,
+    methods: RefCell<HashMap<String, Method>>,
+    spawner: RefCell<Option<Spawner>>,
+    // Remove the script notify handler and the shim once the bridge is dropped.
+    script_notify: RefCell<Option<Subscription>>,
+    init_script: RefCell<Option<Subscription>>,
+}
+
+/// The Rust end of the bridge, for one control.
+///
+/// Cloning it gives another handle to the same bridge. Once every handle has been dropped, the
+/// bridge’s script notify handler is removed, and requests from the page go unanswered.
+pub struct Bridge<C: WebView + 'static> {
+    inner: Rc<BridgeInner<C>>,
+}
+
+impl<C: WebView + 'static> Clone for Bridge<C> {
+    fn clone(&self) -> Bridge<C> {
+        Bridge {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+/// A handle to a bridge that doesn’t keep it alive, from `Bridge::downgrade`.
+///
+/// This is what a method’s handler should hold if it needs the bridge, e.g. to send
+/// notifications: the bridge keeps its handlers alive, so a handler holding a `Bridge` would keep
+/// the bridge alive too, and with it the control.
+pub struct WeakBridge<C: WebView + 'static> {
+    inner: Weak<BridgeInner<C>>,
+}
+
+impl<C: WebView + 'static> Clone for WeakBridge<C> {
+    fn clone(&self) -> WeakBridge<C> {
+        WeakBridge {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<C: WebView + 'static> WeakBridge<C> {
+    /// The bridge, unless every `Bridge` handle to it has been dropped.
+    pub fn upgrade(&self) -> Option<Bridge<C>> {
+        self.inner.upgrade().map(|inner| Bridge { inner })
+    }
+}
+
+impl<C: WebView + 'static> Bridge<C> {
+    /// Create a bridge for the control, adding a script notify handler, and adding the shim as an
+    /// initialization script and running it in the current page.
+    pub fn new(control: C) -> Result<Bridge<C>, Error> {
+        let bridge = Bridge {
+            inner: Rc::new(BridgeInner {
+                control,
+                methods: RefCell::new(HashMap::new()),
+                spawner: RefCell::new(None),
+                script_notify: RefCell::new(None),
+                init_script: RefCell::new(None),
+            }),
+        };
+        let weak = Rc::downgrade(&bridge.inner);
+        let script_notify = bridge.inner.control.add_script_notify(move |message| {
+            if let Some(inner) = Weak::upgrade(&weak) {
+                Bridge { inner }.handle(&message);
+            }
+        })?;
+        *bridge.inner.script_notify.borrow_mut() = Some(script_notify);
+        *bridge.inner.init_script.borrow_mut() = Some(bridge.inner.control.add_init_script(SHIM)?);
+        bridge.inject()?;
+        Ok(bridge)
+    }
+
+    /// Run the shim in the current page. Pages loaded since the bridge was created already have it,
+    /// so this is seldom needed.
+    pub fn inject(&self) -> Result<(), Error> {
+        self.inner.control.run_script(SHIM)
+    }
+
+    /// The control this bridge is for.
+    pub fn control(&self) -> &C {
+        &self.inner.control
+    }
+
+    /// A handle to this bridge that doesn’t keep it alive, for handlers to use.
+    pub fn downgrade(&self) -> WeakBridge<C> {
+        WeakBridge {
+            inner: Rc::downgrade(&self.inner),
+        }
+    }
+
+    /// Register a method, replacing any existing method of the same name.
+    ///
+    /// The params are deserialised as `P` (a tuple for positional params, a struct for named
+    /// ones, or `()` for none); if that fails, the page gets an `INVALID_PARAMS` error.
+    ///
+    /// The bridge keeps the handler until the method is replaced or unregistered, so a handler
+    /// that holds a `Bridge` keeps the bridge and its control alive for good, leaking them. A
+    /// handler that needs the bridge should hold a `WeakBridge` from `downgrade` instead.
+    pub fn register<P, R, F>(&self, method: &str, handler: F)
+    where
+        P: DeserializeOwned,
+        R: Serialize,
+        F: Fn(P) -> Result<R, RpcError> + 'static,
+    {
+        let method_fn: Method = Rc::new(move |params| {
+            MethodOutcome::Ready(
+                deserialize_params(params)
+                    .and_then(&handler)
+                    .and_then(serialize_result),
+            )
+        });
+        self.inner
+            .methods
+            .borrow_mut()
+            .insert(method.to_owned(), method_fn);
+    }
+
+    /// Register an asynchronous method, replacing any existing method of the same name.
+    ///
+    /// This is as `register`, except that the handler returns a future, which is run with the
+    /// spawner given to `set_spawner`. As there, the handler should hold a `WeakBridge` rather
+    /// than a `Bridge`.
+    pub fn register_async<P, R, F, Fut>(&self, method: &str, handler: F)
+    where
+        P: DeserializeOwned,
+        R: Serialize,
+        F: Fn(P) -> Fut + 'static,
+        Fut: Future<Output = Result<R, RpcError>> + 'static,
+    {
+        let method_fn: Method = Rc::new(move |params| match deserialize_params(params) {
+            Ok(params) => {
+                let future = handler(params);
+                MethodOutcome::Pending(Box::pin(
+                    async move { future.await.and_then(serialize_result) },
+                ))
+            }
+            Err(error) => MethodOutcome::Ready(Err(error)),
+        });
+        self.inner
+            .methods
+            .borrow_mut()
+            .insert(method.to_owned(), method_fn);
+    }
+
+    /// Remove a method. Returns false if there was no such method.
+    pub fn unregister(&self, method: &str) -> bool {
+        self.inner.methods.borrow_mut().remove(method).is_some()
+    }
+
+    /// Set the function used to run asynchronous methods, typically by spawning the future on
+    /// the UI thread’s executor, e.g. `glib::MainContext::spawn_local`.
+    ///
+    /// Until this is set, calls to asynchronous methods fail with `INTERNAL_ERROR`.
+    pub fn set_spawner<S>(&self, spawner: S)
+    where
+        S: Fn(LocalFuture) + 'static,
+    {
+        *self.inner.spawner.borrow_mut() = Some(Rc::new(spawner));
+    }
+
+    /// Send a notification to the page, calling the listeners added with `rpc.on(method, …)`.
+    pub fn notify(&self, method: &str, params: impl Serialize) -> Result<(), Error> {
+        self.send(&json!({
+            "jsonrpc": "2.0",
+            "method": method,
+            "params": serde_json::to_value(params)?,
+        }))
+    }
+
+    fn send(&self, message: &Value) -> Result<(), Error> {
+        self.inner.control.run_script(&format!(
+            "window.rpc._receive({});",
+            script::js_literal(message)
+        ))
+    }
+
+    fn handle(&self, message: &str) {
+        let request: Request = match serde_json::from_str(message) {
+            Ok(request) => request,
+            // Not for us: the page is using window.external.notify for something else too.
+            Err(_) => return,
+        };
+        if request.jsonrpc != "2.0" {
+            return;
+        }
+        // Not holding the borrow while the method runs, as it may well register methods.
+        let method = self.inner.methods.borrow().get(&request.method).cloned();
+        let outcome = match method {
+            Some(method) => method(request.params),
+            None => MethodOutcome::Ready(Err(RpcError::new(
+                RpcError::METHOD_NOT_FOUND,
+                format!("method not found: {}", request.method),
+            ))),
+        };
+        let id = request.id;
+        match outcome {
+            MethodOutcome::Ready(result) => self.respond(id, result),
+            MethodOutcome::Pending(future) => {
+                // Not holding the borrow while spawning either, as the spawner may run the future
+                // there and then, and the method may set a new spawner.
+                let spawner = self.inner.spawner.borrow().clone();
+                match spawner {
+                    Some(spawn) => {
+                        let weak = Rc::downgrade(&self.inner);
+                        spawn(Box::pin(async move {
+                            let result = future.await;
+                            if let Some(inner) = Weak::upgrade(&weak) {
+                                Bridge { inner }.respond(id, result);
+                            }
+                        }));
+                    }
+                    None => {
+                        self.respond(
+                            id,
+                            Err(RpcError::new(
+                                RpcError::INTERNAL_ERROR,
+                                "no spawner has been set for asynchronous methods",
+                            )),
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    fn respond(&self, id: Option<Value>, result: Result<Value, RpcError>) {
+        let id = match id {
+            Some(id) => id,
+            None => return,
+        };
+        let response = match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err(error) => json!({ "jsonrpc": "2.0", "id": id, "error": error }),
+        };
+        // This only fails if the control could not be created, in which case no one is listening.
+        let _ = self.send(&response);
+    }
+}
+
+/// Deserialise a field that is there, even if it is null, as `Some`.
+fn deserialize_present<'de, D>(deserializer: D) -> Result<Option<Value>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Value::deserialize(deserializer).map(Some)
+}
+
+fn deserialize_params<P: DeserializeOwned>(params: Value) -> Result<P, RpcError> {
+    serde_json::from_value(params)
+        .map_err(|error| RpcError::new(RpcError::INVALID_PARAMS, error.to_string()))
+}
+
+fn serialize_result<R: Serialize>(result: R) -> Result<Value, RpcError> {
+    serde_json::to_value(result)
+        .map_err(|error| RpcError::new(RpcError::INTERNAL_ERROR, error.to_string()))
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use std::sync::Arc;
+    use std::task::{Context, Wake, Waker};
+
+    use super::*;
+    use crate::mock::{Control, Process};
+    use crate::settings::ControlSettings;
+
+    struct NoopWaker;
+
+    impl Wake for NoopWaker {
+        fn wake(self: Arc<Self>) {}
+    }
+
+    fn noop_waker() -> Waker {
+        Waker::from(Arc::new(NoopWaker))
+    }
+
+    fn bridge() -> (Control, Bridge<Control>) {
+        let control = Process::new()
+            .create_control((0, 0), (800, 600), &ControlSettings::default())
+            .unwrap();
+        control.complete_creation();
+        let bridge = Bridge::new(control.clone()).unwrap();
+        bridge.register("add", |(a, b): (i32, i32)| Ok(a + b));
+        (control, bridge)
+    }
+
+    /// The messages the bridge has sent to the page, after the shim.
+    fn sent(control: &Control) -> Vec<Value> {
+        control
+            .scripts()
+            .iter()
+            .filter_map(|script| {
+                let message = script
+                    .strip_prefix("window.rpc._receive(")?
+                    .strip_suffix(");")?;
+                Some(serde_json::from_str(message).unwrap())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn installs_the_shim() {
+        let (control, bridge) = bridge();
+        assert_eq!(control.init_scripts(), [SHIM]);
+        assert_eq!(control.scripts(), [SHIM]);
+        assert_eq!(control.script_notify_handler_count(), 1);
+        drop(bridge);
+        assert!(control.init_scripts().is_empty());
+        assert_eq!(control.script_notify_handler_count(), 0);
+    }
+
+    #[test]
+    fn calls() {
+        let (control, _bridge) = bridge();
+        control.inject_script_notify(r#"{"jsonrpc":"2.0","id":1,"method":"add","params":[1,2]}"#);
+        control.inject_script_notify(r#"{"jsonrpc":"2.0","id":"two","method":"nope"}"#);
+        control.inject_script_notify(r#"{"jsonrpc":"2.0","id":3,"method":"add","params":["a"]}"#);
+        control
+            .inject_script_notify(r#"{"jsonrpc":"2.0","id":null,"method":"add","params":[2,2]}"#);
+        let sent = sent(&control);
+        assert_eq!(sent[0], json!({ "jsonrpc": "2.0", "id": 1, "result": 3 }));
+        assert_eq!(sent[1]["id"], "two");
+        assert_eq!(sent[1]["error"]["code"], RpcError::METHOD_NOT_FOUND);
+        assert_eq!(sent[2]["id"], 3);
+        assert_eq!(sent[2]["error"]["code"], RpcError::INVALID_PARAMS);
+        assert_eq!(
+            sent[3],
+            json!({ "jsonrpc": "2.0", "id": null, "result": 4 })
+        );
+        assert_eq!(sent.len(), 4);
+    }
+
+    #[test]
+    fn errors_from_methods() {
+        let (control, bridge) = bridge();
+        bridge.register("fail", |(): ()| -> Result<(), RpcError> {
+            Err(RpcError::new(1, "nope").with_data("why"))
+        });
+        control.inject_script_notify(r#"{"jsonrpc":"2.0","id":1,"method":"fail"}"#);
+        assert_eq!(
+            sent(&control),
+            [json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "error": { "code": 1, "message": "nope", "data": "why" },
+            })]
+        );
+    }
+
+    #[test]
+    fn notifications_and_other_messages_get_no_response() {
+        let (control, bridge) = bridge();
+        let logged = Rc::new(RefCell::new(Vec::new()));
+        let logged2 = logged.clone();
+        bridge.register("log", move |(message,): (String,)| {
+            logged2.borrow_mut().push(message);
+            Ok(())
+        });
+        control.inject_script_notify(r#"{"jsonrpc":"2.0","method":"log","params":["hi"]}"#);
+        control.inject_script_notify(r#"{"jsonrpc":"2.0","method":"nope"}"#);
+        control.inject_script_notify(r#"{"jsonrpc":"1.0","id":1,"method":"add","params":[1,2]}"#);
+        control.inject_script_notify("not JSON at all");
+        assert_eq!(*logged.borrow(), ["hi"]);
+        assert!(sent(&control).is_empty());
+    }
+
+    #[test]
+    fn notifying_the_page() {
+        let (control, bridge) = bridge();
+        bridge.notify("tick", (1, "two")).unwrap();
+        assert_eq!(
+            sent(&control),
+            [json!({ "jsonrpc": "2.0", "method": "tick", "params": [1, "two"] })]
+        );
+    }
+
+    #[test]
+    fn asynchronous_methods() {
+        let (control, bridge) = bridge();
+        bridge.register_async("double", |(n,): (i32,)| async move { Ok(n * 2) });
+        control.inject_script_notify(r#"{"jsonrpc":"2.0","id":1,"method":"double","params":[4]}"#);
+        assert_eq!(sent(&control)[0]["error"]["code"], RpcError::INTERNAL_ERROR);
+
+        let spawned = Rc::new(RefCell::new(Vec::new()));
+        let spawned2 = spawned.clone();
+        bridge.set_spawner(move |future| spawned2.borrow_mut().push(future));
+        control.inject_script_notify(r#"{"jsonrpc":"2.0","id":2,"method":"double","params":[4]}"#);
+        assert_eq!(sent(&control).len(), 1);
+        let mut future = spawned.borrow_mut().pop().unwrap();
+        let waker = noop_waker();
+        let mut context = Context::from_waker(&waker);
+        assert!(future.as_mut().poll(&mut context).is_ready());
+        assert_eq!(
+            sent(&control)[1],
+            json!({ "jsonrpc": "2.0", "id": 2, "result": 8 })
+        );
+    }
+
+    #[test]
+    fn spawners_can_run_futures_at_once() {
+        let (control, bridge) = bridge();
+        let run_at_once = |mut future: LocalFuture| {
+            let waker = noop_waker();
+            let mut context = Context::from_waker(&waker);
+            assert!(future.as_mut().poll(&mut context).is_ready());
+        };
+        bridge.set_spawner(run_at_once);
+        let weak = bridge.downgrade();
+        bridge.register_async("respawn", move |(): ()| {
+            let weak = weak.clone();
+            async move {
+                weak.upgrade().unwrap().set_spawner(run_at_once);
+                Ok("done")
+            }
+        });
+        control.inject_script_notify(r#"{"jsonrpc":"2.0","id":1,"method":"respawn"}"#);
+        assert_eq!(
+            sent(&control),
+            [json!({ "jsonrpc": "2.0", "id": 1, "result": "done" })]
+        );
+    }
+
+    #[test]
+    fn handlers_holding_a_weak_bridge_let_it_go() {
+        let (control, bridge) = bridge();
+        let weak = bridge.downgrade();
+        let weak2 = weak.clone();
+        bridge.register("ping", move |(): ()| {
+            weak2.upgrade().unwrap().notify("pong", ()).unwrap();
+            Ok(())
+        });
+        control.inject_script_notify(r#"{"jsonrpc":"2.0","id":1,"method":"ping"}"#);
+        assert_eq!(sent(&control).len(), 2);
+
+        drop(bridge);
+        assert!(weak.upgrade().is_none());
+        control.inject_script_notify(r#"{"jsonrpc":"2.0","id":2,"method":"ping"}"#);
+        assert_eq!(sent(&control).len(), 2);
+    }
+}
@@ -0,0 +1,269 @@
+//! Serving content from Rust under a custom URI scheme, e.g. `app://`, without running a server.
+//!
+//! Register a handler with `WebView::register_scheme`, then navigate to a URL in that scheme:
+//!
+//! ```rust,ignore
+//! control.register_scheme("app", |request: &SchemeRequest| match request.path.as_str() {
+//!     "/" | "/index.html" => SchemeResponse::ok("<h1>Hello!</h1>")
+//!         .with_header("Content-Type", "text/html; charset=utf-8"),
+//!     _ => SchemeResponse::not_found(),
+//! })?;
+//! control.navigate("app://localhost/")?;
+//! ```
+//!
+//! What the page sees varies a little by backend:
+//!
+//! - WebKit2GTK passes on everything: method, headers, status and response headers. Reader bodies
+//!   are streamed.
+//! - EdgeHTML can only do this through its local stream URIs, so navigating to `app://host/path`
+//!   really navigates to an `ms-local-stream:` URI, which is what `url()` will report and what the
+//!   page’s origin will be; only relative links keep working. Requests are always GETs without
+//!   headers, response headers are ignored (the content type is sniffed), an error status fails the
+//!   request, and reader bodies are read into memory before the page gets any of it.
+
+use std::borrow::Cow;
+use std::fmt;
+use std::io::{self, Read};
+
+use crate::error::Error;
+
+/// A request for a resource in a custom scheme.
+#[derive(Debug, Clone)]
+pub struct SchemeRequest {
+    /// The HTTP method, e.g. `GET`.
+    pub method: String,
+    /// The full URI, as the engine reports it.
+    pub uri: String,
+    /// The path part of the URI, e.g. `/index.html` for `app://localhost/index.html`.
+    pub path: String,
+    /// The request headers, in order.
+    pub headers: Vec<(String, String)>,
+}
+
+impl SchemeRequest {
+    /// Get the value of a header, ignoring ASCII case in its name.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header_name, _)| header_name.eq_ignore_ascii_case(name))
+            .map(|(_, value)| &**value)
+    }
+}
+
+/// The response to a `SchemeRequest`.
+#[derive(Debug)]
+pub struct SchemeResponse {
+    /// The HTTP status code.
+    pub status: u16,
+    /// The response headers, in order.
+    pub headers: Vec<(String, String)>,
+    pub body: Body,
+}
+
+impl SchemeResponse {
+    pub fn new(status: u16, body: impl Into<Body>) -> SchemeResponse {
+        SchemeResponse {
+            status,
+            headers: Vec::new(),
+            body: body.into(),
+        }
+    }
+
+    /// A 200 OK response with the given body.
+    pub fn ok(body: impl Into<Body>) -> SchemeResponse {
+        SchemeResponse::new(200, body)
+    }
+
+    /// An empty 404 Not Found response.
+    pub fn not_found() -> SchemeResponse {
+        SchemeResponse::new(404, Body::empty())
+    }
+
+    /// Add a header.
+    pub fn with_header(
+        mut self,
+        name: impl Into<String>,
+        value: impl Into<String>,
+    ) -> SchemeResponse {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Get the value of a header, ignoring ASCII case in its name.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header_name, _)| header_name.eq_ignore_ascii_case(name))
+            .map(|(_, value)| &**value)
+    }
+}
+
+/// The body of a `SchemeResponse`.
+pub enum Body {
+    Bytes(Cow<'static, [u8]>),
+    /// A body to be read as the page wants it, where the backend supports that.
+    Reader(Box<dyn Read + Send>),
+}
+
+impl Body {
+    pub fn empty() -> Body {
+        Body::Bytes(Cow::Borrowed(&[]))
+    }
+
+    pub fn reader(reader: impl Read + Send + 'static) -> Body {
+        Body::Reader(Box::new(reader))
+    }
+
+    /// Get the whole body, reading it if necessary.
+    pub fn into_bytes(self) -> io::Result<Cow<'static, [u8]>> {
+        match self {
+            Body::Bytes(bytes) => Ok(bytes),
+            Body::Reader(mut reader) => {
+                let mut bytes = Vec::new();
+                reader.read_to_end(&mut bytes)?;
+                Ok(Cow::Owned(bytes))
+            }
+        }
+    }
+}
+
+impl fmt::Debug for Body {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Body::Bytes(ref bytes) => write!(f, "Body::Bytes({} bytes)", bytes.len()),
+            Body::Reader(_) => f.write_str("Body::Reader(..)"),
+        }
+    }
+}
+
+impl From<Vec<u8>> for Body {
+    fn from(bytes: Vec<u8>) -> Body {
+        Body::Bytes(Cow::Owned(bytes))
+    }
+}
+
+impl From<&'static [u8]> for Body {
+    fn from(bytes: &'static [u8]) -> Body {
+        Body::Bytes(Cow::Borrowed(bytes))
+    }
+}
+
+impl From<Cow<'static, [u8]>> for Body {
+    fn from(bytes: Cow<'static, [u8]>) -> Body {
+        Body::Bytes(bytes)
+    }
+}
+
+impl From<String> for Body {
+    fn from(string: String) -> Body {
+        Body::Bytes(Cow::Owned(string.into_bytes()))
+    }
+}
+
+impl From<&'static str> for Body {
+    fn from(string: &'static str) -> Body {
+        Body::Bytes(Cow::Borrowed(string.as_bytes()))
+    }
+}
+
+#[cfg_attr(not(any_backend), allow(dead_code))]
+pub(crate) type SchemeHandler = dyn Fn(&SchemeRequest) -> SchemeResponse;
+
+/// Split a URL into its scheme and what comes after the `://`, if it has that form.
+pub(crate) fn split_url(url: &str) -> Option<(&str, &str)> {
+    let colon = url.find("://")?;
+    let scheme = &url[..colon];
+    if scheme.is_empty()
+        || !scheme
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '-' || c == '.')
+    {
+        return None;
+    }
+    Some((scheme, &url[colon + 3..]))
+}
+
+/// Check that a URL can be navigated to: that it starts with a scheme followed by a colon, and
+/// contains no control characters. Engines vary in what they make of anything else, such as
+/// spaces, so it is left to them.
+#[cfg_attr(not(any_backend), allow(dead_code))]
+pub(crate) fn check_url(url: &str) -> Result<(), Error> {
+    let invalid = || Err(Error::InvalidUrl(url.to_owned()));
+    let scheme = match url.find(':') {
+        Some(colon) => &url[..colon],
+        None => return invalid(),
+    };
+    if !scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+        || !scheme
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '-' || c == '.')
+        || url.chars().any(|c| c.is_control())
+    {
+        return invalid();
+    }
+    Ok(())
+}
+
+/// The path of a URL, given what comes after the `://`: from the first slash up to any query or
+/// fragment, or `/` if there is no slash.
+#[cfg_attr(
+    not(any(all(windows, feature = "edgehtml"), feature = "mock")),
+    allow(dead_code)
+)]
+pub(crate) fn path_of(rest: &str) -> &str {
+    let end = rest.find(['?', '#']).unwrap_or(rest.len());
+    let rest = &rest[..end];
+    match rest.find('/') {
+        Some(slash) => &rest[slash..],
+        None => "/",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splitting_urls() {
+        assert_eq!(
+            split_url("app://localhost/index.html"),
+            Some(("app", "localhost/index.html"))
+        );
+        assert_eq!(split_url("web+app://x"), Some(("web+app", "x")));
+        assert_eq!(split_url("about:blank"), None);
+        assert_eq!(split_url("://x"), None);
+        assert_eq!(split_url("a b://x"), None);
+    }
+
+    #[test]
+    fn checking_urls() {
+        assert!(check_url("https://example.com/").is_ok());
+        assert!(check_url("about:blank").is_ok());
+        assert!(check_url("data:text/html,<p>hi there</p>").is_ok());
+        assert!(check_url("example.com").is_err());
+        assert!(check_url("1http://example.com").is_err());
+        assert!(check_url("https://example.com/\n").is_err());
+    }
+
+    #[test]
+    fn paths() {
+        assert_eq!(path_of("localhost/a/b?c#d"), "/a/b");
+        assert_eq!(path_of("localhost"), "/");
+        assert_eq!(path_of("localhost?a/b"), "/");
+        assert_eq!(path_of("localhost#/route"), "/");
+    }
+
+    #[test]
+    fn headers() {
+        let request = SchemeRequest {
+            method: "GET".to_owned(),
+            uri: "app://localhost/".to_owned(),
+            path: "/".to_owned(),
+            headers: vec![("Accept".to_owned(), "text/html".to_owned())],
+        };
+        assert_eq!(request.header("accept"), Some("text/html"));
+        assert_eq!(request.header("Range"), None);
+        let response = SchemeResponse::ok("hi").with_header("Content-Type", "text/plain");
+        assert_eq!(response.header("CONTENT-TYPE"), Some("text/plain"));
+    }
+}
@@ -0,0 +1,201 @@
+//! Running JavaScript and getting its result back, in a form that works the same on every backend.
+//!
+//! The backends can only hand back a string, and don’t agree on how exceptions are reported, so
+//! the script is wrapped in a function that catches any exception and serialises the outcome as
+//! JSON; `Eval` then turns that into the caller’s type.
+//!
+//! (The mock backend has no engine, so it skips the wrapping and resolves evals directly.)
+
+use std::cell::RefCell;
+use std::future::Future;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll, Waker};
+
+use serde::de::DeserializeOwned;
+#[cfg(any(all(windows, feature = "edgehtml"), feature = "gtk-webkit2"))]
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::error::Error;
+
+#[cfg(any(all(windows, feature = "edgehtml"), feature = "gtk-webkit2"))]
+/// Wrap a script so that evaluating it produces a JSON string describing its outcome: either
+/// `{"ok": value}` or `{"error": {"message": …, "stack": …}}`.
+///
+/// The script is evaluated with indirect eval, so it runs in the global scope and its value is
+/// that of its last statement, just as when it’s run directly.
+pub(crate) fn wrap(script: &str) -> String {
+    format!(
+        "(function () {{
+            try {{
+                return JSON.stringify({{ ok: (0, eval)({}) }});
+            }} catch (e) {{
+                return JSON.stringify({{
+                    error: {{ message: String(e), stack: e && e.stack ? String(e.stack) : null }}
+                }});
+            }}
+        }})()",
+        js_literal(&Value::String(script.to_owned()))
+    )
+}
+
+/// A script calling the function with the arguments, which must have been serialised already.
+///
+/// A JSON array is spread as the arguments, and null (which is what `()` serialises to) means no
+/// arguments; anything else is passed as the only argument.
+pub(crate) fn call(function: &str, args: Value) -> String {
+    let args = match args {
+        args @ Value::Array(_) => args,
+        Value::Null => Value::Array(Vec::new()),
+        arg => Value::Array(vec![arg]),
+    };
+    format!("{}(...{})", function, js_literal(&args))
+}
+
+/// JSON is almost a subset of JavaScript, except that older engines (EdgeHTML among them) don’t
+/// allow line and paragraph separators in string literals.
+pub(crate) fn js_literal(value: &Value) -> String {
+    value
+        .to_string()
+        .replace('\u{2028}', "\\u2028")
+        .replace('\u{2029}', "\\u2029")
+}
+
+#[cfg(any(all(windows, feature = "edgehtml"), feature = "gtk-webkit2"))]
+#[derive(Deserialize)]
+struct Outcome {
+    // undefined and functions vanish in JSON.stringify, so a missing value means null.
+    #[serde(default)]
+    ok: Value,
+    error: Option<Exception>,
+}
+
+#[cfg(any(all(windows, feature = "edgehtml"), feature = "gtk-webkit2"))]
+#[derive(Deserialize)]
+struct Exception {
+    message: String,
+    stack: Option<String>,
+}
+
+struct Shared {
+    result: Option<Result<Value, Error>>,
+    waker: Option<Waker>,
+}
+
+/// A future that resolves to the result of a script, deserialised as `T`.
+///
+/// This is returned by `WebView::eval` and `WebView::call`. It must be polled on the thread the
+/// control was created on.
+#[must_use = "futures do nothing unless polled"]
+pub struct Eval<T> {
+    shared: Rc<RefCell<Shared>>,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Eval<T> {
+    /// An evaluation that failed before it could even be started.
+    pub(crate) fn failed(error: Error) -> Eval<T> {
+        Eval {
+            shared: Rc::new(RefCell::new(Shared {
+                result: Some(Err(error)),
+                waker: None,
+            })),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T: DeserializeOwned> Eval<T> {
+    /// Take the result if it has arrived, for callers with no task to poll from.
+    #[cfg_attr(
+        not(all(windows, feature = "edgehtml", feature = "winit")),
+        allow(dead_code)
+    )]
+    pub(crate) fn try_take(&mut self) -> Option<Result<T, Error>> {
+        let result = self.shared.borrow_mut().result.take()?;
+        Some(result.and_then(|value| serde_json::from_value(value).map_err(Into::into)))
+    }
+}
+
+impl<T: DeserializeOwned> Future for Eval<T> {
+    type Output = Result<T, Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<T, Error>> {
+        let mut shared = self.shared.borrow_mut();
+        match shared.result.take() {
+            Some(result) => Poll::Ready(
+                result.and_then(|value| serde_json::from_value(value).map_err(Into::into)),
+            ),
+            None => {
+                shared.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// The backend’s end of an `Eval`.
+#[cfg_attr(not(any_backend), allow(dead_code))]
+pub(crate) struct Resolver {
+    shared: Option<Rc<RefCell<Shared>>>,
+}
+
+/// Create an `Eval` and the `Resolver` for the backend to complete it with.
+#[cfg_attr(not(any_backend), allow(dead_code))]
+pub(crate) fn channel<T>() -> (Resolver, Eval<T>) {
+    let shared = Rc::new(RefCell::new(Shared {
+        result: None,
+        waker: None,
+    }));
+    (
+        Resolver {
+            shared: Some(shared.clone()),
+        },
+        Eval {
+            shared,
+            _marker: PhantomData,
+        },
+    )
+}
+
+#[cfg_attr(not(any_backend), allow(dead_code))]
+impl Resolver {
+    /// Complete the evaluation with the script’s value, or an error.
+    pub(crate) fn resolve(mut self, result: Result<Value, Error>) {
+        if let Some(shared) = self.shared.take() {
+            let mut shared = shared.borrow_mut();
+            shared.result = Some(result);
+            if let Some(waker) = shared.waker.take() {
+                waker.wake();
+            }
+        }
+    }
+
+    #[cfg(any(all(windows, feature = "edgehtml"), feature = "gtk-webkit2"))]
+    /// Complete the evaluation with what a script produced by `wrap` evaluated to.
+    pub(crate) fn resolve_outcome(self, outcome: Result<String, Error>) {
+        let result = outcome.and_then(|outcome| {
+            let outcome: Outcome = serde_json::from_str(&outcome)?;
+            match outcome.error {
+                Some(Exception { message, stack }) => Err(Error::Script { message, stack }),
+                None => Ok(outcome.ok),
+            }
+        });
+        self.resolve(result);
+    }
+}
+
+impl Drop for Resolver {
+    // The backend dropped the evaluation without running it, which happens when the control could
+    // not be created.
+    fn drop(&mut self) {
+        if let Some(shared) = self.shared.take() {
+            Resolver {
+                shared: Some(shared),
+            }
+            .resolve(Err(Error::CreationFailed));
+        }
+    }
+}
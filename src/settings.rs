@@ -0,0 +1,158 @@
+//! Settings for a control, applied when it is created.
+//!
+//! ```rust,ignore
+//! let settings = ControlSettings::new()
+//!     .dev_tools_enabled(cfg!(debug_assertions))
+//!     .context_menu_enabled(false);
+//! let control = process.create_control(hwnd_type, position, size, &settings)?;
+//! for setting in control.unsupported_settings() {
+//!     eprintln!("{:?} couldn’t be applied", setting);
+//! }
+//! ```
+//!
+//! Anything not set is left as the engine has it by default. Not every engine can honour every
+//! setting; those it can’t are ignored, and reported by `WebView::unsupported_settings`.
+
+/// One of the settings in `ControlSettings`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Setting {
+    JavaScript,
+    ScriptNotify,
+    IndexedDb,
+    ContextMenu,
+    DevTools,
+    UserAgent,
+    Autoplay,
+}
+
+/// Whether media may start playing without the user asking it to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Autoplay {
+    Allow,
+    /// Allow it, but only if it is muted.
+    AllowWithoutSound,
+    Deny,
+}
+
+/// The settings to create a control with.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ControlSettings {
+    pub(crate) javascript_enabled: Option<bool>,
+    pub(crate) script_notify_allowed: Option<bool>,
+    pub(crate) indexed_db_enabled: Option<bool>,
+    pub(crate) context_menu_enabled: Option<bool>,
+    pub(crate) dev_tools_enabled: Option<bool>,
+    pub(crate) user_agent: Option<String>,
+    pub(crate) autoplay: Option<Autoplay>,
+}
+
+impl ControlSettings {
+    /// Settings that leave everything as the engine has it.
+    pub fn new() -> ControlSettings {
+        ControlSettings::default()
+    }
+
+    /// Set whether pages can run scripts. `run_script` and `eval` still work either way.
+    pub fn javascript_enabled(mut self, enabled: bool) -> ControlSettings {
+        self.javascript_enabled = Some(enabled);
+        self
+    }
+
+    /// Set whether pages can call `window.external.notify`, raising ScriptNotify.
+    pub fn script_notify_allowed(mut self, allowed: bool) -> ControlSettings {
+        self.script_notify_allowed = Some(allowed);
+        self
+    }
+
+    /// Set whether pages can use IndexedDB.
+    pub fn indexed_db_enabled(mut self, enabled: bool) -> ControlSettings {
+        self.indexed_db_enabled = Some(enabled);
+        self
+    }
+
+    /// Set whether right-clicking shows the engine’s context menu.
+    pub fn context_menu_enabled(mut self, enabled: bool) -> ControlSettings {
+        self.context_menu_enabled = Some(enabled);
+        self
+    }
+
+    /// Set whether the user can open the engine’s developer tools, e.g. to inspect elements.
+    pub fn dev_tools_enabled(mut self, enabled: bool) -> ControlSettings {
+        self.dev_tools_enabled = Some(enabled);
+        self
+    }
+
+    /// Set the user agent string sent with requests and reported to scripts.
+    pub fn user_agent(mut self, user_agent: impl Into<String>) -> ControlSettings {
+        self.user_agent = Some(user_agent.into());
+        self
+    }
+
+    /// Set whether media can play automatically.
+    pub fn autoplay(mut self, autoplay: Autoplay) -> ControlSettings {
+        self.autoplay = Some(autoplay);
+        self
+    }
+
+    /// The settings that have been set, in the order of `Setting`.
+    pub fn settings(&self) -> Vec<Setting> {
+        let mut settings = Vec::new();
+        let mut add = |setting, is_set: bool| {
+            if is_set {
+                settings.push(setting);
+            }
+        };
+        add(Setting::JavaScript, self.javascript_enabled.is_some());
+        add(Setting::ScriptNotify, self.script_notify_allowed.is_some());
+        add(Setting::IndexedDb, self.indexed_db_enabled.is_some());
+        add(Setting::ContextMenu, self.context_menu_enabled.is_some());
+        add(Setting::DevTools, self.dev_tools_enabled.is_some());
+        add(Setting::UserAgent, self.user_agent.is_some());
+        add(Setting::Autoplay, self.autoplay.is_some());
+        settings
+    }
+
+    /// The settings that have been set but that a backend, which supports those given, can’t
+    /// honour.
+    #[cfg_attr(not(any_backend), allow(dead_code))]
+    pub(crate) fn unsupported(&self, supported: &[Setting]) -> Vec<Setting> {
+        self.settings()
+            .into_iter()
+            .filter(|setting| !supported.contains(setting))
+            .collect()
+    }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use super::*;
+    use crate::mock::Process;
+    use crate::WebView;
+
+    #[test]
+    fn settings_the_engine_cant_honour_are_reported() {
+        let settings = ControlSettings::new()
+            .user_agent("test")
+            .javascript_enabled(false)
+            .autoplay(Autoplay::Deny);
+        assert_eq!(
+            settings.settings(),
+            [Setting::JavaScript, Setting::UserAgent, Setting::Autoplay]
+        );
+
+        let process = Process::with_supported_settings(&[Setting::JavaScript, Setting::DevTools]);
+        let control = process
+            .create_control((0, 0), (800, 600), &settings)
+            .unwrap();
+        assert_eq!(
+            control.unsupported_settings(),
+            [Setting::UserAgent, Setting::Autoplay]
+        );
+        assert_eq!(control.settings(), settings);
+
+        let control = Process::new()
+            .create_control((0, 0), (800, 600), &settings)
+            .unwrap();
+        assert!(control.unsupported_settings().is_empty());
+    }
+}
@@ -0,0 +1,48 @@
+use std::fmt;
+
+/// An event handler (or initialization script) added to a control, which is removed when this is
+/// dropped.
+///
+/// To keep the handler for as long as the control lives instead, call `detach()`.
+#[must_use = "the handler is removed as soon as the subscription is dropped; \
+              use detach() to keep it"]
+pub struct Subscription {
+    remove: Option<Box<dyn FnOnce()>>,
+}
+
+impl Subscription {
+    /// A subscription that calls `remove` when dropped.
+    #[cfg_attr(not(any_backend), allow(dead_code))]
+    pub(crate) fn new<F>(remove: F) -> Subscription
+    where
+        F: FnOnce() + 'static,
+    {
+        Subscription {
+            remove: Some(Box::new(remove)),
+        }
+    }
+
+    /// Remove the handler now. This is the same as dropping the subscription.
+    pub fn unsubscribe(self) {}
+
+    /// Keep the handler for as long as the control lives.
+    pub fn detach(mut self) {
+        self.remove = None;
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        if let Some(remove) = self.remove.take() {
+            remove();
+        }
+    }
+}
+
+impl fmt::Debug for Subscription {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Subscription")
+            .field("attached", &self.remove.is_some())
+            .finish()
+    }
+}
@@ -0,0 +1,119 @@
+//! Stopping the user from zooming the page, for `WebView::disable_user_zoom`.
+
+use crate::error::Error;
+use crate::subscription::Subscription;
+use crate::WebView;
+
+/// Cancels the events that zoom the page: ctrl+wheel (which is also how EdgeHTML delivers touchpad
+/// pinches) and ctrl with plus, minus or zero. Running it again, in a document that already has
+/// it, just turns it back on.
+const DISABLE: &str = "\
+    (function () {
+        if ('__webviewcontrolNoUserZoom' in window) {
+            window.__webviewcontrolNoUserZoom = true;
+            return;
+        }
+        window.__webviewcontrolNoUserZoom = true;
+        addEventListener('wheel', function (event) {
+            if (event.ctrlKey && window.__webviewcontrolNoUserZoom) {
+                event.preventDefault();
+            }
+        }, { capture: true, passive: false });
+        addEventListener('keydown', function (event) {
+            if (event.ctrlKey && window.__webviewcontrolNoUserZoom
+                    && ['+', '=', '-', '_', '0'].indexOf(event.key) !== -1) {
+                event.preventDefault();
+            }
+        }, true);
+    })();";
+
+const ENABLE: &str = "window.__webviewcontrolNoUserZoom = false;";
+
+/// Fail with `Error::InvalidZoom` unless the zoom factor is positive and finite.
+#[cfg_attr(not(any_backend), allow(dead_code))]
+pub(crate) fn check_zoom(zoom: f64) -> Result<(), Error> {
+    if zoom.is_finite() && zoom > 0.0 {
+        Ok(())
+    } else {
+        Err(Error::InvalidZoom(zoom))
+    }
+}
+
+pub(crate) fn disable_user_zoom<C>(control: &C) -> Result<Subscription, Error>
+where
+    C: WebView + Clone + 'static,
+{
+    // An initialization script gets in before the page’s own scripts, but one that can’t be
+    // removed would go on disabling zoom in every page after the subscription is dropped, so
+    // then the script is run as each page starts loading instead.
+    let for_new_pages = if control.capabilities().init_script_removal {
+        control.add_init_script(DISABLE)?
+    } else {
+        let page_control = control.clone();
+        control.add_content_loading(move |_event| {
+            let _ = page_control.run_script(DISABLE);
+        })?
+    };
+    control.run_script(DISABLE)?;
+    let control = control.clone();
+    Ok(Subscription::new(move || {
+        drop(for_new_pages);
+        let _ = control.run_script(ENABLE);
+    }))
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use super::*;
+    use crate::capabilities::Capabilities;
+    use crate::mock::{Control, Process};
+    use crate::settings::ControlSettings;
+
+    fn control(capabilities: Capabilities) -> Control {
+        let control = Process::new()
+            .create_control((0, 0), (800, 600), &ControlSettings::default())
+            .unwrap();
+        control.set_capabilities(capabilities);
+        control.complete_creation();
+        control
+    }
+
+    fn disables(control: &Control) -> usize {
+        control
+            .scripts()
+            .iter()
+            .filter(|script| *script == DISABLE)
+            .count()
+    }
+
+    #[test]
+    fn with_init_script_removal() {
+        let control = control(Capabilities::ALL);
+        let subscription = control.disable_user_zoom().unwrap();
+        assert_eq!(control.init_scripts(), [DISABLE]);
+        assert_eq!(disables(&control), 1);
+        drop(subscription);
+        assert!(control.init_scripts().is_empty());
+        assert_eq!(control.scripts().last().map(|s| &**s), Some(ENABLE));
+    }
+
+    #[test]
+    fn without_init_script_removal() {
+        let control = control(Capabilities {
+            init_script_removal: false,
+            ..Capabilities::ALL
+        });
+        let subscription = control.disable_user_zoom().unwrap();
+        assert!(control.init_scripts().is_empty());
+        assert_eq!(disables(&control), 1);
+        control.navigate("https://example.com/").unwrap();
+        control.finish_navigation(200);
+        assert_eq!(disables(&control), 2);
+
+        drop(subscription);
+        assert_eq!(control.scripts().last().map(|s| &**s), Some(ENABLE));
+        control.navigate("https://example.com/").unwrap();
+        control.finish_navigation(200);
+        assert_eq!(disables(&control), 2);
+    }
+}